@@ -0,0 +1,21 @@
+//! Runs operation scripts on a list cast from arbitrary account data.
+//!
+//! No operation may panic or loop forever whatever the header and links hold. A list that
+//! passes `validate` must also stay valid through every operation.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use openbook_v2_cu_fuzz::{account, apply_list, ops, List};
+
+fuzz_target!(|data: &[u8]| {
+    let (mut list, script) = account::<List>(data);
+    let valid = list.validate().is_ok();
+
+    for op in ops(script) {
+        apply_list(&mut list, op);
+        if valid {
+            list.validate().unwrap();
+        }
+    }
+});
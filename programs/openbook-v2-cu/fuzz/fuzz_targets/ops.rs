@@ -0,0 +1,32 @@
+//! Runs operation scripts on freshly initialized queues, which must stay consistent.
+
+#![no_main]
+
+use bytemuck::Zeroable;
+use libfuzzer_sys::fuzz_target;
+use openbook_v2_cu::state::Queue;
+use openbook_v2_cu_fuzz::{apply_list, apply_ring, apply_tombstone, ops, List, Ring, Tombstones};
+
+fuzz_target!(|script: &[u8]| {
+    let mut ring = Ring::zeroed();
+    Queue::init(&mut ring);
+    let mut list = List::zeroed();
+    list.init();
+    let mut tombstones = Tombstones::zeroed();
+    Queue::init(&mut tombstones);
+
+    for op in ops(script) {
+        apply_ring(&mut ring, op);
+        assert!(ring.len() <= ring.capacity());
+        assert_eq!(ring.iter().count(), ring.len());
+
+        apply_list(&mut list, op);
+        list.validate().unwrap();
+        assert_eq!(list.iter().count(), list.len());
+
+        apply_tombstone(&mut tombstones, op);
+        assert!(tombstones.span() <= tombstones.capacity());
+        assert!(tombstones.tombstone_count() <= tombstones.len());
+        assert_eq!(tombstones.iter().count(), tombstones.len());
+    }
+});
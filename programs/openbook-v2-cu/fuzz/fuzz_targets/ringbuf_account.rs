@@ -0,0 +1,18 @@
+//! Runs operation scripts on a ring buffer cast from arbitrary account data.
+//!
+//! No operation may panic or loop forever whatever the header holds.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use openbook_v2_cu_fuzz::{account, apply_ring, ops, Ring};
+
+fuzz_target!(|data: &[u8]| {
+    let (mut ring, script) = account::<Ring>(data);
+
+    for op in ops(script) {
+        apply_ring(&mut ring, op);
+        let _ = ring.peek_front();
+        let _ = ring.peek_back();
+    }
+});
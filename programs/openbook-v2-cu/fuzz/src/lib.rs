@@ -0,0 +1,137 @@
+//! Interprets fuzzer input as queue accounts and scripts of operations on them.
+
+use bytemuck::Pod;
+use openbook_v2_cu::state::{DLLEventQueue, EventQueue, Queue, SlotIndex, TombstoneEventQueue};
+
+/// Small enough for the fuzzer to fill the queues and wrap around them.
+pub const CAPACITY: usize = 8;
+
+pub type Ring = EventQueue<CAPACITY, u64>;
+pub type List = DLLEventQueue<CAPACITY, u16, u64>;
+pub type Tombstones = TombstoneEventQueue<CAPACITY, u64>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Push,
+    Pop,
+    PopBack,
+    RemoveAt(usize),
+    DeleteSlot(usize),
+    RevertPushes(usize),
+    Iterate,
+}
+
+/// Decodes one operation per byte: the low 3 bits pick the operation and the high 5 bits its
+/// argument, which reaches past the capacity. The largest argument stands for the `NULL`
+/// slot.
+pub fn ops(script: &[u8]) -> impl Iterator<Item = Op> + '_ {
+    script.iter().map(|&byte| {
+        let arg = (byte >> 3) as usize;
+        match byte & 7 {
+            0 | 1 => Op::Push,
+            2 => Op::Pop,
+            3 => Op::RemoveAt(arg),
+            4 if arg == 31 => Op::DeleteSlot(u16::NULL.to_usize()),
+            4 => Op::DeleteSlot(arg),
+            5 => Op::RevertPushes(arg),
+            6 => Op::PopBack,
+            _ => Op::Iterate,
+        }
+    })
+}
+
+/// Casts the start of `data` into a `Q` as if it were the account data, zero-padding short
+/// inputs, and returns the rest as a script.
+pub fn account<Q: Pod>(data: &[u8]) -> (Q, &[u8]) {
+    let mut queue = Q::zeroed();
+    let bytes = bytemuck::bytes_of_mut(&mut queue);
+    let len = bytes.len().min(data.len());
+    bytes[..len].copy_from_slice(&data[..len]);
+    (queue, &data[len..])
+}
+
+/// Visits the whole queue from both ends, which must yield exactly the length the iterator
+/// reports, at most `len` events.
+pub fn iterate(queue: &impl Queue) {
+    let len = queue.iter().len();
+    assert!(len <= queue.len());
+    assert_eq!(queue.iter().count(), len);
+    assert_eq!(queue.iter().rev().count(), len);
+}
+
+/// Applies `op` to a ring buffer, ignoring the errors it returns.
+pub fn apply_ring(queue: &mut Ring, op: Op) {
+    match op {
+        Op::Push => {
+            let _ = queue.push_back(queue.header.seq_num);
+        }
+        Op::Pop => {
+            let _ = queue.pop_front();
+        }
+        Op::PopBack => {
+            let _ = queue.pop_back();
+        }
+        Op::RemoveAt(index) => {
+            let _ = Queue::remove_at(queue, index);
+        }
+        Op::DeleteSlot(_) => {}
+        Op::RevertPushes(len) => {
+            let _ = queue.revert_pushes(len);
+        }
+        Op::Iterate => iterate(queue),
+    }
+}
+
+/// Applies `op` to a tombstone ring buffer, ignoring the errors it returns.
+pub fn apply_tombstone(queue: &mut Tombstones, op: Op) {
+    match op {
+        Op::Push => {
+            let _ = queue.push_back(queue.header.seq_num);
+        }
+        Op::Pop => {
+            let _ = queue.pop_front();
+        }
+        Op::PopBack => {
+            let _ = queue.pop_back();
+        }
+        Op::RemoveAt(index) => {
+            let _ = queue.remove_at(index);
+        }
+        Op::DeleteSlot(_) | Op::RevertPushes(_) => {}
+        Op::Iterate => {
+            iterate(queue);
+            let _ = queue.peek_front();
+            let _ = queue.peek_back();
+        }
+    }
+}
+
+/// Applies `op` to a list, ignoring the errors it returns.
+pub fn apply_list(queue: &mut List, op: Op) {
+    match op {
+        Op::Push => {
+            let _ = queue.push_back(queue.header.seq_num);
+        }
+        Op::Pop => {
+            let _ = queue.delete();
+        }
+        Op::PopBack => {
+            let _ = queue.pop_back();
+        }
+        Op::RemoveAt(index) => {
+            let _ = Queue::remove_at(queue, index);
+        }
+        Op::DeleteSlot(slot) => {
+            let _ = queue.delete_slot(slot);
+        }
+        Op::RevertPushes(_) => {}
+        Op::Iterate => {
+            iterate(queue);
+            let _ = queue.front();
+            let _ = queue.back();
+            for slot in 0..=CAPACITY {
+                let _ = queue.at(slot);
+            }
+        }
+    }
+}
@@ -0,0 +1,485 @@
+use crate::{
+    error::{BenchError, QueueError},
+    state::{
+        Capacity, DLLEventQueue, EventQueue, Queue, QueueItem, SlotIndex, TombstoneCapacity,
+        TombstoneEventQueue,
+    },
+};
+use anchor_lang::prelude::*;
+use bytemuck::Pod;
+use openbook_v2::state::{AnyEvent, OutEvent, Side};
+
+/// Item the benchmark scenario can fill a queue with.
+pub trait BenchItem: Pod + std::fmt::Debug {
+    fn new(i: usize, seq_num: u64) -> Self;
+}
+
+impl BenchItem for AnyEvent {
+    fn new(i: usize, seq_num: u64) -> Self {
+        let event = OutEvent::new(
+            Side::Bid,
+            0,
+            0,
+            seq_num,
+            Pubkey::from([i as u8; 32]),
+            i.try_into().unwrap(),
+        );
+        bytemuck::cast(event)
+    }
+}
+
+/// Opaque items of any size, to measure how the item size affects each operation.
+impl<const SIZE: usize> BenchItem for [u8; SIZE] {
+    fn new(i: usize, _seq_num: u64) -> Self {
+        [i as u8; SIZE]
+    }
+}
+
+/// How a backend removes the items the scenario picks at random positions, so that only the
+/// removals themselves are measured.
+pub trait RandomRemoval: Queue {
+    /// Keys identifying the items at `positions` for [`Self::remove_key`], each position
+    /// indexing the queue left by the previous removals. Resolved before the measurement.
+    fn removal_keys(&self, positions: &[usize]) -> Vec<usize>;
+
+    fn remove_key(&mut self, key: usize) -> Result<Self::Item>;
+}
+
+/// The ring buffer removes by position.
+impl<const N: usize, T: QueueItem> RandomRemoval for EventQueue<N, T> {
+    fn removal_keys(&self, positions: &[usize]) -> Vec<usize> {
+        positions.to_vec()
+    }
+
+    fn remove_key(&mut self, index: usize) -> Result<T> {
+        self.remove_at(index)
+    }
+}
+
+/// The tombstone ring buffer removes by position too.
+impl<const N: usize, T: QueueItem> RandomRemoval for TombstoneEventQueue<N, T>
+where
+    Capacity<N>: TombstoneCapacity,
+{
+    fn removal_keys(&self, positions: &[usize]) -> Vec<usize> {
+        positions.to_vec()
+    }
+
+    fn remove_key(&mut self, index: usize) -> Result<T> {
+        self.remove_at(index)
+    }
+}
+
+/// The list removes by slot, like a consumer that kept the slots of its events, so walking
+/// from a position to its slot is left out of the measurement.
+impl<const N: usize, I: SlotIndex, T: QueueItem> RandomRemoval for DLLEventQueue<N, I, T> {
+    fn removal_keys(&self, positions: &[usize]) -> Vec<usize> {
+        let mut slots: Vec<usize> = self.iter().map(|e| e.slot).collect();
+        positions.iter().map(|&pos| slots.remove(pos)).collect()
+    }
+
+    fn remove_key(&mut self, slot: usize) -> Result<T> {
+        self.delete_slot(slot)
+    }
+}
+
+/// Operations performed by one run of the benchmark scenario.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Workload {
+    /// Seed of the generator picking the removed positions.
+    pub seed: u64,
+    /// Number of items pushed into the empty queue, at most its capacity.
+    pub inserts: u32,
+    /// Number of items removed at random positions, at most `inserts`.
+    pub removals: u32,
+    /// Number of items visited by each measured iteration, at most `inserts`.
+    pub iterations: Vec<u32>,
+}
+
+impl Workload {
+    /// Fills the queue, removes 20 random items and iterates 10 items then the whole queue.
+    pub fn full(capacity: usize) -> Self {
+        Self {
+            seed: 0,
+            inserts: capacity as u32,
+            removals: 20.min(capacity as u32),
+            iterations: vec![10.min(capacity as u32), capacity as u32],
+        }
+    }
+}
+
+/// SplitMix64 generator, cheap enough to run on chain and identical on every platform.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform-ish value in `0..n`; the modulo bias is irrelevant for picking positions.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// Positions to remove one after the other from a queue of `len` items, each one in range
+/// of the queue left by the previous removals.
+pub fn random_positions(rng: &mut Rng, len: usize, removals: usize) -> Vec<usize> {
+    (0..removals).map(|k| rng.below(len - k)).collect()
+}
+
+fn fill<Q>(queue: &mut Q, len: usize) -> Result<()>
+where
+    Q: Queue,
+    Q::Item: BenchItem,
+{
+    for i in queue.len()..len {
+        let item = Q::Item::new(i, queue.seq_num());
+        queue
+            .push_back(item)
+            .map_err(|err| error!(QueueError::from(err)))?;
+    }
+    Ok(())
+}
+
+/// Logs the benchmark name of `queue` as `<name>_<capacity>_<item size>`.
+fn report<Q: Queue>(name: &str, queue: &Q) {
+    let item_size = std::mem::size_of::<Q::Item>();
+    crate::cu::bench(format!("{}_{}_{}", name, queue.capacity(), item_size));
+}
+
+/// Runs the benchmark scenario on `queue`, measuring each step with [`cu_section!`].
+///
+/// Every backend goes through the exact same operations under the same section labels, so
+/// their numbers can be compared line by line. Random removals go through
+/// [`RandomRemoval`], which removes from the list by slot.
+pub fn run<Q>(name: &str, queue: &mut Q, workload: &Workload) -> Result<()>
+where
+    Q: RandomRemoval,
+    Q::Item: BenchItem,
+{
+    let capacity = queue.capacity();
+    let inserts = workload.inserts as usize;
+    let removals = workload.removals as usize;
+    require!(inserts <= capacity, BenchError::InvalidWorkload);
+    require!(removals <= inserts, BenchError::InvalidWorkload);
+    require!(
+        workload.iterations.iter().all(|&n| n as usize <= inserts),
+        BenchError::InvalidWorkload
+    );
+
+    report(name, queue);
+    let positions = random_positions(&mut Rng::new(workload.seed), inserts, removals);
+
+    cu_section!("Initialize", {
+        queue.init();
+    });
+
+    cu_section!(format!("Inserting_{}", inserts), {
+        fill(queue, inserts)?;
+    });
+
+    let keys = queue.removal_keys(&positions);
+    cu_section!(format!("Removing_{}_random_positions", removals), {
+        for key in keys {
+            queue.remove_key(key)?;
+        }
+    });
+
+    fill(queue, inserts)?;
+
+    for &n in &workload.iterations {
+        let n = n as usize;
+        cu_section!(format!("Iterating_{}", n), {
+            assert_eq!(queue.iter().take(n).count(), n);
+        });
+    }
+
+    for &n in &workload.iterations {
+        let n = n as usize;
+        cu_section!(format!("IteratingBack_{}", n), {
+            assert_eq!(queue.iter().rev().take(n).count(), n);
+        });
+    }
+
+    cu_section!(format!("Deleting_{}", queue.len()), {
+        for _ in 0..queue.len() {
+            queue.pop_front()?;
+        }
+    });
+
+    fill(queue, inserts)?;
+
+    cu_section!(format!("DeletingBack_{}", queue.len()), {
+        for _ in 0..queue.len() {
+            queue.pop_back()?;
+        }
+    });
+
+    Ok(())
+}
+
+/// Resets a persisted queue, measured as `Initialize`.
+pub fn init<Q: Queue>(name: &str, queue: &mut Q) {
+    report(name, queue);
+    cu_section!("Initialize", {
+        queue.init();
+    });
+}
+
+/// Pushes `n` items onto a persisted queue, measured as `Push_<n>_at_<len>`.
+pub fn push_n<Q>(name: &str, queue: &mut Q, n: usize) -> Result<()>
+where
+    Q: Queue,
+    Q::Item: BenchItem,
+{
+    let len = queue.len();
+    report(name, queue);
+    cu_section!(format!("Push_{}_at_{}", n, len), {
+        fill(queue, len + n)?;
+    });
+    Ok(())
+}
+
+/// Pops `n` items off a persisted queue, measured as `Pop_<n>_at_<len>`.
+pub fn pop_n<Q: Queue>(name: &str, queue: &mut Q, n: usize) -> Result<()> {
+    let len = queue.len();
+    report(name, queue);
+    cu_section!(format!("Pop_{}_at_{}", n, len), {
+        for _ in 0..n {
+            queue.pop_front()?;
+        }
+    });
+    Ok(())
+}
+
+/// Pops `n` items off the back of a persisted queue, newest first, measured as
+/// `PopBack_<n>_at_<len>`.
+pub fn pop_back_n<Q: Queue>(name: &str, queue: &mut Q, n: usize) -> Result<()> {
+    let len = queue.len();
+    report(name, queue);
+    cu_section!(format!("PopBack_{}_at_{}", n, len), {
+        for _ in 0..n {
+            queue.pop_back()?;
+        }
+    });
+    Ok(())
+}
+
+/// Removes the items at `positions` one after the other from a persisted queue, measured as
+/// `RemoveAt_<count>_at_<len>`.
+pub fn remove_at<Q: Queue>(name: &str, queue: &mut Q, positions: &[u32]) -> Result<()> {
+    let len = queue.len();
+    report(name, queue);
+    cu_section!(format!("RemoveAt_{}_at_{}", positions.len(), len), {
+        for &pos in positions {
+            queue.remove_at(pos as usize)?;
+        }
+    });
+    Ok(())
+}
+
+/// Visits the first `n` items of a persisted queue, measured as `Iterate_<n>_at_<len>`.
+pub fn iterate_n<Q: Queue>(name: &str, queue: &Q, n: usize) -> Result<()> {
+    let len = queue.len();
+    require!(n <= len, QueueError::IndexOutOfRange);
+    report(name, queue);
+    cu_section!(format!("Iterate_{}_at_{}", n, len), {
+        assert_eq!(queue.iter().take(n).count(), n);
+    });
+    Ok(())
+}
+
+/// Visits the last `n` items of a persisted queue newest first, measured as
+/// `IterateBack_<n>_at_<len>`.
+pub fn iterate_back_n<Q: Queue>(name: &str, queue: &Q, n: usize) -> Result<()> {
+    let len = queue.len();
+    require!(n <= len, QueueError::IndexOutOfRange);
+    report(name, queue);
+    cu_section!(format!("IterateBack_{}_at_{}", n, len), {
+        assert_eq!(queue.iter().rev().take(n).count(), n);
+    });
+    Ok(())
+}
+
+/// Truncates a persisted ring buffer to `len` items, measured as `RevertPushes_<n>_at_<len>`
+/// with `n` the number of reverted pushes.
+pub fn revert_pushes<const N: usize, T: QueueItem>(
+    name: &str,
+    queue: &mut EventQueue<N, T>,
+    len: usize,
+) -> Result<()> {
+    let current = queue.len();
+    report(name, queue);
+    cu_section!(
+        format!(
+            "RevertPushes_{}_at_{}",
+            current.saturating_sub(len),
+            current
+        ),
+        {
+            queue.revert_pushes(len)?;
+        }
+    );
+    Ok(())
+}
+
+/// Deletes the item stored in `slot` of a persisted list, measured as `DeleteSlot_1_at_<len>`.
+pub fn delete_slot<const N: usize, I: SlotIndex, T: QueueItem>(
+    name: &str,
+    queue: &mut DLLEventQueue<N, I, T>,
+    slot: usize,
+) -> Result<()> {
+    let len = queue.len();
+    report(name, queue);
+    cu_section!(format!("DeleteSlot_1_at_{}", len), {
+        queue.delete_slot(slot)?;
+    });
+    Ok(())
+}
+
+/// Reads every event of a persisted list by slot, once through the checked [`DLLEventQueue::at`]
+/// and once through [`DLLEventQueue::at_unchecked`], measured as `CheckedAt_<len>_at_<len>`
+/// and `UncheckedAt_<len>_at_<len>`.
+pub fn access_slots<const N: usize, I: SlotIndex, T: QueueItem>(
+    name: &str,
+    queue: &DLLEventQueue<N, I, T>,
+) -> Result<()> {
+    let len = queue.len();
+    let slots: Vec<usize> = queue.iter().map(|e| e.slot).collect();
+    // Folds the first byte of every event so that the reads are not optimized away.
+    let first_byte = |item: &T| bytemuck::bytes_of(item).first().copied().unwrap_or(0);
+    report(name, queue);
+
+    let mut checked = 0u8;
+    cu_section!(format!("CheckedAt_{}_at_{}", len, len), {
+        for &slot in &slots {
+            checked ^= first_byte(queue.at(slot)?);
+        }
+    });
+
+    let mut unchecked = 0u8;
+    cu_section!(format!("UncheckedAt_{}_at_{}", len, len), {
+        for &slot in &slots {
+            // SAFETY: the slots were just yielded by the iterator of the unchanged queue.
+            unchecked ^= first_byte(unsafe { queue.at_unchecked(slot) });
+        }
+    });
+
+    assert_eq!(checked, unchecked);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{TombstoneEventQueue, MAX_NUM_EVENTS};
+    use bytemuck::Zeroable;
+
+    #[test]
+    fn positions_are_reproducible_and_in_range() {
+        let positions = random_positions(&mut Rng::new(7), 64, 64);
+        assert_eq!(positions, random_positions(&mut Rng::new(7), 64, 64));
+        assert_ne!(positions, random_positions(&mut Rng::new(8), 64, 64));
+        for (k, pos) in positions.into_iter().enumerate() {
+            assert!(pos < 64 - k);
+        }
+    }
+
+    #[test]
+    fn list_removes_by_slot_the_items_at_the_positions() {
+        let positions = random_positions(&mut Rng::new(5), 40, 20);
+        let mut ring = EventQueue::<64, [u8; 32]>::zeroed();
+        let mut list = DLLEventQueue::<64, u16, [u8; 32]>::zeroed();
+        Queue::init(&mut ring);
+        Queue::init(&mut list);
+        fill(&mut ring, 40).unwrap();
+        fill(&mut list, 40).unwrap();
+
+        let ring_keys = ring.removal_keys(&positions);
+        let list_keys = list.removal_keys(&positions);
+        assert_ne!(ring_keys, list_keys);
+        for (ring_key, list_key) in ring_keys.into_iter().zip(list_keys) {
+            assert_eq!(
+                ring.remove_key(ring_key).unwrap(),
+                list.remove_key(list_key).unwrap()
+            );
+        }
+        assert!(ring.iter().eq(Queue::iter(&list)));
+    }
+
+    #[test]
+    fn scenario_runs_on_every_backend() {
+        let workload = Workload::full(MAX_NUM_EVENTS);
+        run("RingBuf", &mut <EventQueue>::zeroed(), &workload).unwrap();
+        run("DLList", &mut <DLLEventQueue>::zeroed(), &workload).unwrap();
+        run("Tombstone", &mut <TombstoneEventQueue>::zeroed(), &workload).unwrap();
+
+        let workload = Workload {
+            seed: 3,
+            inserts: 40,
+            removals: 40,
+            iterations: vec![0, 40],
+        };
+        run(
+            "RingBuf",
+            &mut EventQueue::<64, [u8; 32]>::zeroed(),
+            &workload,
+        )
+        .unwrap();
+        run("DLList", &mut DLLEventQueue::<64, u32>::zeroed(), &workload).unwrap();
+        run(
+            "Tombstone",
+            &mut TombstoneEventQueue::<64, [u8; 32]>::zeroed(),
+            &workload,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn rejects_workload_exceeding_capacity() {
+        let workload = Workload::full(65);
+        assert_eq!(
+            run("RingBuf", &mut EventQueue::<64>::zeroed(), &workload).unwrap_err(),
+            BenchError::InvalidWorkload.into()
+        );
+    }
+
+    #[test]
+    fn isolated_operations_share_a_queue() {
+        let mut queue = DLLEventQueue::<64>::zeroed();
+        init("DLList", &mut queue);
+        push_n("DLList", &mut queue, 40).unwrap();
+        remove_at("DLList", &mut queue, &[39, 0, 17]).unwrap();
+        iterate_n("DLList", &queue, 37).unwrap();
+        iterate_back_n("DLList", &queue, 37).unwrap();
+        pop_n("DLList", &mut queue, 20).unwrap();
+        pop_back_n("DLList", &mut queue, 10).unwrap();
+        assert_eq!(queue.len(), 7);
+        access_slots("DLList", &queue).unwrap();
+
+        let full: Error = QueueError::QueueFull.into();
+        let empty: Error = QueueError::QueueEmpty.into();
+        let out_of_range: Error = QueueError::IndexOutOfRange.into();
+        assert_eq!(iterate_n("DLList", &queue, 8).unwrap_err(), out_of_range);
+        assert_eq!(
+            iterate_back_n("DLList", &queue, 8).unwrap_err(),
+            out_of_range
+        );
+        assert_eq!(
+            remove_at("DLList", &mut queue, &[7]).unwrap_err(),
+            out_of_range
+        );
+        assert_eq!(pop_n("DLList", &mut queue, 8).unwrap_err(), empty);
+        assert_eq!(push_n("DLList", &mut queue, 65).unwrap_err(), full);
+    }
+}
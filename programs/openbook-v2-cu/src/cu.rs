@@ -0,0 +1,60 @@
+use anchor_lang::prelude::msg;
+use solana_program::log::sol_log_compute_units;
+use std::fmt::Display;
+
+/// Compute units the runtime charges for a single `sol_log_compute_units` syscall.
+///
+/// The closing reading of a section pays for itself before logging the remaining budget,
+/// so every measured delta is inflated by exactly this amount.
+pub const LOG_COMPUTE_UNITS_COST: u64 = 100;
+
+/// Number of empty sections measured by the `calibrate` instruction.
+pub const CALIBRATION_RUNS: usize = 16;
+/// Label of the empty sections measured by the `calibrate` instruction.
+pub const CALIBRATION_SECTION: &str = "Empty";
+
+/// Names the measurements that follow in place of the instruction name.
+///
+/// Logs `#bench <name>`, so a single instruction can report several configurations of the
+/// same scenario, e.g. one per queue capacity.
+pub fn bench(name: impl Display) {
+    msg!("#bench {}", name);
+}
+
+/// Guard measuring the compute units spent until it is dropped.
+///
+/// Logs `#begin <label>` followed by the opening budget reading on creation, and the
+/// closing reading followed by `#end <overhead>` on drop. Both markers are logged outside
+/// the two readings, so label formatting never counts towards the section.
+#[must_use = "the section ends when the guard is dropped"]
+pub struct CuSection(());
+
+impl CuSection {
+    pub fn begin(label: impl Display) -> Self {
+        msg!("#begin {}", label);
+        sol_log_compute_units();
+        Self(())
+    }
+}
+
+impl Drop for CuSection {
+    fn drop(&mut self) {
+        sol_log_compute_units();
+        msg!("#end {}", LOG_COMPUTE_UNITS_COST);
+    }
+}
+
+/// Measures the compute units spent in `$body`, see [`CuSection`].
+///
+/// ```ignore
+/// let popped = cu_section!(format!("Deleting_{}", queue.len()), {
+///     queue.pop_front()?
+/// });
+/// ```
+#[macro_export]
+macro_rules! cu_section {
+    ($label:expr, $body:block) => {{
+        let _section = $crate::cu::CuSection::begin($label);
+        $body
+    }};
+}
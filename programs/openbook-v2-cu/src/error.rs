@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+/// Failures of the queue operations.
+#[error_code]
+pub enum QueueError {
+    #[msg("the queue is empty")]
+    QueueEmpty,
+    #[msg("the queue is full")]
+    QueueFull,
+    #[msg("the slot is already free")]
+    SlotAlreadyFree,
+    #[msg("the slot is out of range")]
+    SlotOutOfRange,
+    #[msg("the index is out of range")]
+    IndexOutOfRange,
+    #[msg("cannot revert more pushes than the queue holds")]
+    RevertLengthTooLarge,
+    #[msg("the slot holds no event")]
+    SlotFree,
+    #[msg("the queue links are inconsistent")]
+    CorruptedQueue,
+}
+
+/// Push rejected by a queue, handing the event back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushError<T> {
+    /// The queue holds as many events as it can.
+    Full(T),
+    /// The header or the links point out of range, or the sequence number would overflow.
+    Corrupted(T),
+}
+
+impl<T> PushError<T> {
+    pub fn into_event(self) -> T {
+        match self {
+            Self::Full(event) | Self::Corrupted(event) => event,
+        }
+    }
+}
+
+impl<T> From<PushError<T>> for QueueError {
+    fn from(err: PushError<T>) -> Self {
+        match err {
+            PushError::Full(_) => Self::QueueFull,
+            PushError::Corrupted(_) => Self::CorruptedQueue,
+        }
+    }
+}
+
+/// Invalid arguments of the benchmark instructions.
+#[error_code(offset = 6100)]
+pub enum BenchError {
+    #[msg("unsupported queue capacity or item size")]
+    UnsupportedLayout,
+    #[msg("the account size does not match the queue layout")]
+    AccountSizeMismatch,
+    #[msg("the workload does not fit the queue")]
+    InvalidWorkload,
+}
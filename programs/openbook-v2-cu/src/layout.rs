@@ -0,0 +1,135 @@
+use crate::state::MAX_NUM_EVENTS;
+use anchor_lang::prelude::*;
+
+/// Queue capacities the benchmark instructions can be run with.
+pub const CAPACITIES: [usize; 6] = [64, 256, MAX_NUM_EVENTS, 1024, 2048, 4096];
+/// Size of an `AnyEvent`, the item of an actual event queue.
+pub const EVENT_SIZE: usize = std::mem::size_of::<openbook_v2::state::AnyEvent>();
+/// Item sizes in bytes the benchmark instructions can be run with.
+pub const ITEM_SIZES: [usize; 4] = [32, 88, EVENT_SIZE, 512];
+
+/// Evaluates `$body` with `$n` bound to the const `$capacity`, or `$unsupported` if it is not
+/// one of [`CAPACITIES`].
+macro_rules! with_capacity {
+    ($capacity:expr, $n:ident => $body:expr, _ => $unsupported:expr) => {
+        match $capacity as usize {
+            64 => {
+                const $n: usize = 64;
+                $body
+            }
+            256 => {
+                const $n: usize = 256;
+                $body
+            }
+            $crate::state::MAX_NUM_EVENTS => {
+                const $n: usize = $crate::state::MAX_NUM_EVENTS;
+                $body
+            }
+            1024 => {
+                const $n: usize = 1024;
+                $body
+            }
+            2048 => {
+                const $n: usize = 2048;
+                $body
+            }
+            4096 => {
+                const $n: usize = 4096;
+                $body
+            }
+            _ => $unsupported,
+        }
+    };
+}
+
+/// Evaluates `$body` with `$t` bound to an item type of `$item_size` bytes, or `$unsupported`
+/// if it is not one of [`ITEM_SIZES`]. Items of [`EVENT_SIZE`] are actual `AnyEvent`s.
+macro_rules! with_item_size {
+    ($item_size:expr, $t:ident => $body:expr, _ => $unsupported:expr) => {
+        match $item_size as usize {
+            32 => {
+                type $t = [u8; 32];
+                $body
+            }
+            88 => {
+                type $t = [u8; 88];
+                $body
+            }
+            $crate::layout::EVENT_SIZE => {
+                type $t = openbook_v2::state::AnyEvent;
+                $body
+            }
+            512 => {
+                type $t = [u8; 512];
+                $body
+            }
+            _ => $unsupported,
+        }
+    };
+}
+
+/// Evaluates `$body` with `$q` bound to the queue type described by the [`QueueLayout`]
+/// `$layout`, or `$unsupported` if its capacity or item size is not supported.
+macro_rules! with_queue {
+    ($layout:expr, $q:ident => $body:expr, _ => $unsupported:expr) => {{
+        let layout: &$crate::layout::QueueLayout = &$layout;
+        with_capacity!(layout.capacity, N => with_item_size!(layout.item_size, T => {
+            match layout.kind {
+                $crate::layout::QueueKind::RingBuf => {
+                    type $q = $crate::state::EventQueue<N, T>;
+                    $body
+                }
+                $crate::layout::QueueKind::DLList => {
+                    type $q = $crate::state::DLLEventQueue<N, u16, T>;
+                    $body
+                }
+                $crate::layout::QueueKind::Tombstone => {
+                    type $q = $crate::state::TombstoneEventQueue<N, T>;
+                    $body
+                }
+            }
+        }, _ => $unsupported), _ => $unsupported)
+    }};
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueKind {
+    /// `EventQueue`
+    RingBuf,
+    /// `DLLEventQueue`
+    DLList,
+    /// `TombstoneEventQueue`
+    Tombstone,
+}
+
+impl QueueKind {
+    pub const ALL: [Self; 3] = [Self::RingBuf, Self::DLList, Self::Tombstone];
+
+    /// Benchmark name the measurements on this kind of queue are reported under.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::RingBuf => "RingBuf",
+            Self::DLList => "DLList",
+            Self::Tombstone => "Tombstone",
+        }
+    }
+}
+
+/// Queue type held by a persisted account, selecting the monomorphization an instruction
+/// operates on.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueLayout {
+    pub kind: QueueKind,
+    /// One of [`CAPACITIES`].
+    pub capacity: u32,
+    /// One of [`ITEM_SIZES`].
+    pub item_size: u32,
+}
+
+impl QueueLayout {
+    /// Size of an account holding this queue, including its discriminator, or `None` if the
+    /// layout is not supported.
+    pub fn account_len(&self) -> Option<usize> {
+        with_queue!(self, Q => Some(8 + std::mem::size_of::<Q>()), _ => None)
+    }
+}
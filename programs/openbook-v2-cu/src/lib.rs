@@ -1,180 +1,252 @@
 use anchor_lang::prelude::*;
-use openbook_v2::state::{OutEvent, Side};
-use solana_program::log::sol_log_compute_units;
 
-mod state;
+#[macro_use]
+pub mod cu;
+#[macro_use]
+pub mod layout;
+pub mod bench;
+pub mod error;
+pub mod state;
+use bench::Workload;
+use error::{BenchError, QueueError};
+pub use layout::*;
 use state::*;
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
-fn random_positions() -> Vec<usize> {
-    vec![
-        1, 41, 223, 4, 2, 293, 300, 483, 10, 23, 45, 20, 146, 342, 123, 435, 112, 234, 211, 89,
-    ]
+/// Loads the queue held by `account`, checking that its size matches a `Q`.
+fn loader<'info, Q>(account: &AccountInfo<'info>) -> Result<AccountLoader<'info, Q>>
+where
+    Q: anchor_lang::ZeroCopy + Owner,
+{
+    require_eq!(
+        account.data_len(),
+        8 + std::mem::size_of::<Q>(),
+        BenchError::AccountSizeMismatch
+    );
+    AccountLoader::try_from_unchecked(&crate::id(), account)
+}
+
+/// Runs the benchmark scenario on a zeroed account holding a `Q`.
+fn bench<'info, Q>(name: &str, account: &AccountInfo<'info>, workload: &Workload) -> Result<()>
+where
+    Q: anchor_lang::ZeroCopy + Owner + bench::RandomRemoval,
+    Q::Item: bench::BenchItem,
+{
+    let loader = loader::<Q>(account)?;
+    let mut queue = loader.load_init()?;
+    bench::run(name, &mut *queue, workload)
 }
 
 #[program]
 pub mod openbook_v2_cu {
     use super::*;
 
-    pub fn ring_buf(ctx: Context<RingBuf>) -> Result<()> {
-        let mut event_queue = ctx.accounts.event_queue.load_init()?;
-        let random = random_positions();
-
-        msg!("# Inserting_{}", MAX_NUM_EVENTS);
-        sol_log_compute_units();
-        for i in 0..MAX_NUM_EVENTS {
-            let event = OutEvent::new(
-                Side::Bid,
-                0,
-                0,
-                event_queue.header.seq_num,
-                Pubkey::from([i as u8; 32]),
-                i.try_into().unwrap(),
-            );
-            event_queue.push_back(bytemuck::cast(event)).unwrap();
-        }
-        sol_log_compute_units();
-
-        let target = Pubkey::from([1u8; 32]);
-        let current_len = event_queue.header.count();
-
-        msg!("# Removing_{}_random_positions", random.len());
-        sol_log_compute_units();
-        let mut sorted = random.clone();
-        sorted.sort();
-        for (i, pos) in sorted.into_iter().enumerate() {
-            let position_after_resizes = pos - i;
-            event_queue.buf.swap(0, position_after_resizes);
-            event_queue.pop_front().unwrap();
-        }
-        sol_log_compute_units();
-
-        let current_len = event_queue.header.count();
-        for i in current_len..MAX_NUM_EVENTS {
-            let event = OutEvent::new(
-                Side::Bid,
-                0,
-                0,
-                event_queue.header.seq_num,
-                Pubkey::from([i as u8; 32]),
-                i.try_into().unwrap(),
-            );
-            event_queue.push_back(bytemuck::cast(event)).unwrap();
+    pub fn calibrate(_ctx: Context<Calibrate>) -> Result<()> {
+        for _ in 0..cu::CALIBRATION_RUNS {
+            cu_section!(cu::CALIBRATION_SECTION, {});
         }
+        Ok(())
+    }
 
-        let n = 10;
-        msg!("# Iterating_{}", n);
-        sol_log_compute_units();
-        assert_eq!(event_queue.iter().take(n).count(), n);
-        sol_log_compute_units();
-
-        msg!("# Iterating_{}", MAX_NUM_EVENTS);
-        sol_log_compute_units();
-        assert_eq!(event_queue.iter().count(), MAX_NUM_EVENTS);
-        sol_log_compute_units();
-
-        msg!("# Deleting_{}", event_queue.header.count());
-        sol_log_compute_units();
-        for _ in 0..event_queue.header.count() {
-            event_queue.pop_front().unwrap();
-        }
-        sol_log_compute_units();
+    pub fn ring_buf(
+        ctx: Context<RingBuf>,
+        capacity: u32,
+        item_size: u32,
+        workload: Workload,
+    ) -> Result<()> {
+        with_capacity!(capacity, N => with_item_size!(item_size, T => {
+            let name = QueueKind::RingBuf.name();
+            bench::<EventQueue<N, T>>(name, &ctx.accounts.event_queue, &workload)
+        }, _ => err!(BenchError::UnsupportedLayout)),
+        _ => err!(BenchError::UnsupportedLayout))
+    }
 
-        Ok(())
+    pub fn d_l_list(
+        ctx: Context<DLList>,
+        capacity: u32,
+        item_size: u32,
+        workload: Workload,
+    ) -> Result<()> {
+        with_capacity!(capacity, N => with_item_size!(item_size, T => {
+            let name = QueueKind::DLList.name();
+            bench::<DLLEventQueue<N, u16, T>>(name, &ctx.accounts.event_queue, &workload)
+        }, _ => err!(BenchError::UnsupportedLayout)),
+        _ => err!(BenchError::UnsupportedLayout))
     }
 
-    pub fn d_l_list(ctx: Context<DLList>) -> Result<()> {
-        let mut event_queue = ctx.accounts.event_queue.load_init()?;
-        let random = random_positions();
-
-        msg!("# Initialize");
-        sol_log_compute_units();
-        event_queue.init();
-        sol_log_compute_units();
-
-        msg!("# Inserting_{}", MAX_NUM_EVENTS);
-        sol_log_compute_units();
-        for i in 0..MAX_NUM_EVENTS {
-            let event = OutEvent::new(
-                Side::Bid,
-                0,
-                0,
-                event_queue.header.seq_num,
-                Pubkey::from([i as u8; 32]),
-                i.try_into().unwrap(),
-            );
-            event_queue.push_back(bytemuck::cast(event));
-        }
-        sol_log_compute_units();
+    pub fn tombstone(
+        ctx: Context<Tombstone>,
+        capacity: u32,
+        item_size: u32,
+        workload: Workload,
+    ) -> Result<()> {
+        with_capacity!(capacity, N => with_item_size!(item_size, T => {
+            let name = QueueKind::Tombstone.name();
+            bench::<TombstoneEventQueue<N, T>>(name, &ctx.accounts.event_queue, &workload)
+        }, _ => err!(BenchError::UnsupportedLayout)),
+        _ => err!(BenchError::UnsupportedLayout))
+    }
 
-        msg!("# Removing_{}_random_positions", random.len());
-        sol_log_compute_units();
-        for pos in random {
-            event_queue.delete_slot(pos).unwrap();
-        }
-        sol_log_compute_units();
-
-        let current_len = event_queue.header.count();
-        for i in current_len..MAX_NUM_EVENTS {
-            let event = OutEvent::new(
-                Side::Bid,
-                0,
-                0,
-                event_queue.header.seq_num,
-                Pubkey::from([i as u8; 32]),
-                i.try_into().unwrap(),
-            );
-            event_queue.push_back(bytemuck::cast(event));
-        }
+    /// Initializes a queue account for the isolated operations below, resetting it if it was
+    /// initialized before.
+    pub fn init_queue(ctx: Context<QueueOp>, layout: QueueLayout) -> Result<()> {
+        with_queue!(layout, Q => {
+            let loader = loader::<Q>(&ctx.accounts.queue)?;
+            // `load_init` refuses an account that already has a discriminator
+            ctx.accounts.queue.try_borrow_mut_data()?.fill(0);
+            {
+                let mut queue = loader.load_init()?;
+                bench::init(layout.kind.name(), &mut *queue);
+            }
+            // the discriminator is only written on exit, and the other instructions check it
+            loader.exit(&crate::id())
+        }, _ => err!(BenchError::UnsupportedLayout))
+    }
 
-        let n = 10;
-        msg!("# Iterating_{}", n);
-        sol_log_compute_units();
-        assert_eq!(event_queue.iter().take(n).count(), n);
-        sol_log_compute_units();
-
-        msg!("# Iterating_{}", MAX_NUM_EVENTS);
-        sol_log_compute_units();
-        assert_eq!(event_queue.iter().count(), MAX_NUM_EVENTS);
-        sol_log_compute_units();
-
-        msg!("# Deleting_{}", event_queue.header.count());
-        sol_log_compute_units();
-        for _ in 0..event_queue.header.count() {
-            event_queue.delete().unwrap();
-        }
-        sol_log_compute_units();
-        Ok(())
+    pub fn push_n(ctx: Context<QueueOp>, layout: QueueLayout, n: u32) -> Result<()> {
+        with_queue!(layout, Q => {
+            let loader = loader::<Q>(&ctx.accounts.queue)?;
+            let mut queue = loader.load_mut()?;
+            bench::push_n(layout.kind.name(), &mut *queue, n as usize)
+        }, _ => err!(BenchError::UnsupportedLayout))
+    }
+
+    pub fn pop_n(ctx: Context<QueueOp>, layout: QueueLayout, n: u32) -> Result<()> {
+        with_queue!(layout, Q => {
+            let loader = loader::<Q>(&ctx.accounts.queue)?;
+            let mut queue = loader.load_mut()?;
+            bench::pop_n(layout.kind.name(), &mut *queue, n as usize)
+        }, _ => err!(BenchError::UnsupportedLayout))
+    }
+
+    pub fn pop_back_n(ctx: Context<QueueOp>, layout: QueueLayout, n: u32) -> Result<()> {
+        with_queue!(layout, Q => {
+            let loader = loader::<Q>(&ctx.accounts.queue)?;
+            let mut queue = loader.load_mut()?;
+            bench::pop_back_n(layout.kind.name(), &mut *queue, n as usize)
+        }, _ => err!(BenchError::UnsupportedLayout))
+    }
+
+    /// Removes the items at `positions` in turn, each one indexing the queue left by the
+    /// previous removals.
+    pub fn remove_at(
+        ctx: Context<QueueOp>,
+        layout: QueueLayout,
+        positions: Vec<u32>,
+    ) -> Result<()> {
+        with_queue!(layout, Q => {
+            let loader = loader::<Q>(&ctx.accounts.queue)?;
+            let mut queue = loader.load_mut()?;
+            bench::remove_at(layout.kind.name(), &mut *queue, &positions)
+        }, _ => err!(BenchError::UnsupportedLayout))
+    }
+
+    pub fn iterate_n(ctx: Context<QueueOp>, layout: QueueLayout, n: u32) -> Result<()> {
+        with_queue!(layout, Q => {
+            let loader = loader::<Q>(&ctx.accounts.queue)?;
+            let queue = loader.load()?;
+            bench::iterate_n(layout.kind.name(), &*queue, n as usize)
+        }, _ => err!(BenchError::UnsupportedLayout))
+    }
+
+    pub fn iterate_back_n(ctx: Context<QueueOp>, layout: QueueLayout, n: u32) -> Result<()> {
+        with_queue!(layout, Q => {
+            let loader = loader::<Q>(&ctx.accounts.queue)?;
+            let queue = loader.load()?;
+            bench::iterate_back_n(layout.kind.name(), &*queue, n as usize)
+        }, _ => err!(BenchError::UnsupportedLayout))
+    }
+
+    /// Truncates a default-layout ring buffer to `len` items.
+    pub fn revert_pushes(ctx: Context<QueueOp>, len: u32) -> Result<()> {
+        let loader = loader::<EventQueue>(&ctx.accounts.queue)?;
+        let mut queue = loader.load_mut()?;
+        bench::revert_pushes(QueueKind::RingBuf.name(), &mut *queue, len as usize)
+    }
+
+    /// Deletes the item stored in `slot` of a default-layout list.
+    pub fn delete_slot(ctx: Context<QueueOp>, slot: u32) -> Result<()> {
+        let loader = loader::<DLLEventQueue>(&ctx.accounts.queue)?;
+        let mut queue = loader.load_mut()?;
+        bench::delete_slot(QueueKind::DLList.name(), &mut *queue, slot as usize)
+    }
+
+    /// Reads every event of a default-layout list by slot, with and without bounds checks.
+    pub fn access_slots(ctx: Context<QueueOp>) -> Result<()> {
+        let loader = loader::<DLLEventQueue>(&ctx.accounts.queue)?;
+        let queue = loader.load()?;
+        bench::access_slots(QueueKind::DLList.name(), &*queue)
+    }
+
+    /// Checks the links of a list, logging the first inconsistency found.
+    pub fn validate_list(ctx: Context<QueueOp>, layout: QueueLayout) -> Result<()> {
+        require!(
+            layout.kind == QueueKind::DLList,
+            BenchError::UnsupportedLayout
+        );
+        with_capacity!(layout.capacity, N => with_item_size!(layout.item_size, T => {
+            let loader = loader::<DLLEventQueue<N, u16, T>>(&ctx.accounts.queue)?;
+            let queue = loader.load()?;
+            queue.validate().map_err(|corruption| {
+                msg!("{}", corruption);
+                error!(QueueError::CorruptedQueue)
+            })
+        }, _ => err!(BenchError::UnsupportedLayout)),
+        _ => err!(BenchError::UnsupportedLayout))
     }
 }
 
+#[derive(Accounts)]
+pub struct Calibrate {}
+
 #[derive(Accounts)]
 pub struct RingBuf<'info> {
-    #[account(zero)]
-    event_queue: AccountLoader<'info, EventQueue>,
+    /// CHECK: zeroed `EventQueue` of the requested layout, loaded by the instruction
+    #[account(mut)]
+    event_queue: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
 pub struct DLList<'info> {
-    #[account(zero)]
-    event_queue: AccountLoader<'info, DLLEventQueue>,
+    /// CHECK: zeroed `DLLEventQueue` of the requested layout, loaded by the instruction
+    #[account(mut)]
+    event_queue: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Tombstone<'info> {
+    /// CHECK: zeroed `TombstoneEventQueue` of the requested layout, loaded by the instruction
+    #[account(mut)]
+    event_queue: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct QueueOp<'info> {
+    /// CHECK: queue of the layout passed to the instruction, loaded by the instruction
+    #[account(mut)]
+    queue: UncheckedAccount<'info>,
 }
 
 #[cfg(test)]
 mod comp_budget {
     use super::*;
+    use crate::error::QueueError;
     use anchor_lang::InstructionData;
-    use solana_program_test::{tokio, ProgramTest};
+    use solana_program_test::{tokio, BanksClientError, ProgramTest, ProgramTestContext};
     use solana_sdk::{
         account::Account,
-        instruction::{AccountMeta, Instruction},
+        compute_budget::ComputeBudgetInstruction,
+        instruction::{AccountMeta, Instruction, InstructionError},
         pubkey::Pubkey,
         rent::Rent,
         signature::Signer,
-        transaction::Transaction,
+        transaction::{Transaction, TransactionError},
     };
-    use std::mem::size_of;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Counts the transactions sent, see [`try_send_instruction`].
+    static NONCE: AtomicU32 = AtomicU32::new(0);
 
     fn zero_account(len: usize) -> Account {
         Account {
@@ -185,53 +257,274 @@ mod comp_budget {
         }
     }
 
-    async fn send_instruction(
-        context: &mut solana_program_test::ProgramTestContext,
+    async fn try_send_instruction(
+        context: &mut ProgramTestContext,
         data: Vec<u8>,
-        pubkey: Pubkey,
-    ) {
-        let accounts = vec![AccountMeta::new(pubkey, false)];
+        accounts: Vec<AccountMeta>,
+    ) -> std::result::Result<(), BanksClientError> {
+        // a distinct limit per transaction keeps repeated instructions, e.g. deleting the same
+        // slot twice, from being rejected as already processed
+        let limit = 1_400_000 - NONCE.fetch_add(1, Ordering::Relaxed);
         let ix = Instruction::new_with_bytes(crate::id(), &data, accounts);
         let tx = Transaction::new_signed_with_payer(
-            &[ix],
+            &[ComputeBudgetInstruction::set_compute_unit_limit(limit), ix],
             Some(&context.payer.pubkey()),
             &[&context.payer],
             context.last_blockhash,
         );
 
-        context
-            .banks_client
-            .process_transactions(vec![tx])
+        context.banks_client.process_transactions(vec![tx]).await
+    }
+
+    async fn send_instruction(
+        context: &mut ProgramTestContext,
+        data: Vec<u8>,
+        accounts: Vec<AccountMeta>,
+    ) {
+        try_send_instruction(context, data, accounts).await.unwrap();
+    }
+
+    /// Sends an instruction expected to fail and returns its custom error code.
+    async fn send_failing_instruction(
+        context: &mut ProgramTestContext,
+        data: Vec<u8>,
+        accounts: Vec<AccountMeta>,
+    ) -> u32 {
+        let err = try_send_instruction(context, data, accounts)
             .await
-            .unwrap();
+            .unwrap_err();
+        match err.unwrap() {
+            TransactionError::InstructionError(_, InstructionError::Custom(code)) => code,
+            err => panic!("unexpected error {:?}", err),
+        }
     }
 
     #[tokio::test]
     async fn event_queue() {
-        let ringbuf_pubkey = Pubkey::new_unique();
-        let ringbuf_account = zero_account(8 + size_of::<crate::state::EventQueue>());
+        let mut program = ProgramTest::default();
+        program.add_program("openbook_v2_cu", crate::id(), None);
 
-        let list_pubkey = Pubkey::new_unique();
-        let list_account = zero_account(8 + size_of::<crate::state::DLLEventQueue>());
+        // the full scenario on the larger queues does not fit in the compute budget of a
+        // transaction, cu-matrix measures them one operation at a time instead
+        let mut queues = Vec::new();
+        for capacity in CAPACITIES.into_iter().filter(|&capacity| capacity <= 1024) {
+            for item_size in ITEM_SIZES {
+                let ringbuf_pubkey = Pubkey::new_unique();
+                let ringbuf_len = with_capacity!(capacity, N => with_item_size!(item_size, T => {
+                    EventQueue::<N, T>::SIZE
+                }, _ => unreachable!()), _ => unreachable!());
+                program.add_account(ringbuf_pubkey, zero_account(8 + ringbuf_len));
+
+                let list_pubkey = Pubkey::new_unique();
+                let list_len = with_capacity!(capacity, N => with_item_size!(item_size, T => {
+                    DLLEventQueue::<N, u16, T>::SIZE
+                }, _ => unreachable!()), _ => unreachable!());
+                program.add_account(list_pubkey, zero_account(8 + list_len));
+
+                let tombstone_pubkey = Pubkey::new_unique();
+                let tombstone_len = with_capacity!(capacity, N => with_item_size!(item_size, T => {
+                    TombstoneEventQueue::<N, T>::SIZE
+                }, _ => unreachable!()), _ => unreachable!());
+                program.add_account(tombstone_pubkey, zero_account(8 + tombstone_len));
+
+                queues.push((
+                    capacity as u32,
+                    item_size as u32,
+                    ringbuf_pubkey,
+                    list_pubkey,
+                    tombstone_pubkey,
+                ));
+            }
+        }
+
+        let mut context = program.start_with_context().await;
+
+        send_instruction(
+            &mut context,
+            crate::instruction::Calibrate {}.data(),
+            vec![],
+        )
+        .await;
 
+        for (capacity, item_size, ringbuf_pubkey, list_pubkey, tombstone_pubkey) in queues {
+            send_instruction(
+                &mut context,
+                crate::instruction::RingBuf {
+                    capacity,
+                    item_size,
+                    workload: Workload::full(capacity as usize),
+                }
+                .data(),
+                vec![AccountMeta::new(ringbuf_pubkey, false)],
+            )
+            .await;
+
+            send_instruction(
+                &mut context,
+                crate::instruction::DLList {
+                    capacity,
+                    item_size,
+                    workload: Workload::full(capacity as usize),
+                }
+                .data(),
+                vec![AccountMeta::new(list_pubkey, false)],
+            )
+            .await;
+
+            send_instruction(
+                &mut context,
+                crate::instruction::Tombstone {
+                    capacity,
+                    item_size,
+                    workload: Workload::full(capacity as usize),
+                }
+                .data(),
+                vec![AccountMeta::new(tombstone_pubkey, false)],
+            )
+            .await;
+        }
+    }
+
+    #[tokio::test]
+    async fn isolated_operations() {
         let mut program = ProgramTest::default();
         program.add_program("openbook_v2_cu", crate::id(), None);
-        program.add_account(ringbuf_pubkey, ringbuf_account);
-        program.add_account(list_pubkey, list_account);
+
+        let layouts: Vec<(QueueLayout, Pubkey)> = QueueKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let layout = QueueLayout {
+                    kind,
+                    capacity: MAX_NUM_EVENTS as u32,
+                    item_size: EVENT_SIZE as u32,
+                };
+                let pubkey = Pubkey::new_unique();
+                program.add_account(pubkey, zero_account(layout.account_len().unwrap()));
+                (layout, pubkey)
+            })
+            .collect();
 
         let mut context = program.start_with_context().await;
 
+        for (layout, pubkey) in layouts {
+            let accounts = vec![AccountMeta::new(pubkey, false)];
+            let instructions = [
+                crate::instruction::InitQueue { layout }.data(),
+                crate::instruction::PushN { layout, n: 400 }.data(),
+                crate::instruction::RemoveAt {
+                    layout,
+                    positions: vec![0, 200, 397],
+                }
+                .data(),
+                crate::instruction::IterateN { layout, n: 397 }.data(),
+                crate::instruction::IterateBackN { layout, n: 397 }.data(),
+                crate::instruction::PopN { layout, n: 200 }.data(),
+                crate::instruction::PopBackN { layout, n: 100 }.data(),
+            ];
+            for data in instructions {
+                send_instruction(&mut context, data, accounts.clone()).await;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn queue_errors() {
+        let mut program = ProgramTest::default();
+        program.add_program("openbook_v2_cu", crate::id(), None);
+
+        let ringbuf = QueueLayout {
+            kind: QueueKind::RingBuf,
+            capacity: MAX_NUM_EVENTS as u32,
+            item_size: EVENT_SIZE as u32,
+        };
+        let list = QueueLayout {
+            kind: QueueKind::DLList,
+            ..ringbuf
+        };
+        let ringbuf_pubkey = Pubkey::new_unique();
+        let list_pubkey = Pubkey::new_unique();
+        program.add_account(ringbuf_pubkey, zero_account(ringbuf.account_len().unwrap()));
+        program.add_account(list_pubkey, zero_account(list.account_len().unwrap()));
+
+        let mut context = program.start_with_context().await;
+
+        let accounts = vec![AccountMeta::new(ringbuf_pubkey, false)];
+        let layout = ringbuf;
+        send_instruction(
+            &mut context,
+            crate::instruction::InitQueue { layout }.data(),
+            accounts.clone(),
+        )
+        .await;
+        let code = send_failing_instruction(
+            &mut context,
+            crate::instruction::PopN { layout, n: 1 }.data(),
+            accounts.clone(),
+        )
+        .await;
+        assert_eq!(code, u32::from(QueueError::QueueEmpty));
+        send_instruction(
+            &mut context,
+            crate::instruction::PushN { layout, n: 5 }.data(),
+            accounts.clone(),
+        )
+        .await;
+        let code = send_failing_instruction(
+            &mut context,
+            crate::instruction::RevertPushes { len: 6 }.data(),
+            accounts.clone(),
+        )
+        .await;
+        assert_eq!(code, u32::from(QueueError::RevertLengthTooLarge));
+
+        let accounts = vec![AccountMeta::new(list_pubkey, false)];
+        let layout = list;
+        send_instruction(
+            &mut context,
+            crate::instruction::InitQueue { layout }.data(),
+            accounts.clone(),
+        )
+        .await;
         send_instruction(
             &mut context,
-            crate::instruction::RingBuf {}.data(),
-            ringbuf_pubkey,
+            crate::instruction::PushN { layout, n: 5 }.data(),
+            accounts.clone(),
         )
         .await;
+        send_instruction(
+            &mut context,
+            crate::instruction::DeleteSlot { slot: 2 }.data(),
+            accounts.clone(),
+        )
+        .await;
+        let code = send_failing_instruction(
+            &mut context,
+            crate::instruction::DeleteSlot { slot: 2 }.data(),
+            accounts.clone(),
+        )
+        .await;
+        assert_eq!(code, u32::from(QueueError::SlotAlreadyFree));
+        let code = send_failing_instruction(
+            &mut context,
+            crate::instruction::DeleteSlot {
+                slot: MAX_NUM_EVENTS as u32,
+            }
+            .data(),
+            accounts.clone(),
+        )
+        .await;
+        assert_eq!(code, u32::from(QueueError::SlotOutOfRange));
 
         send_instruction(
             &mut context,
-            crate::instruction::DLList {}.data(),
-            list_pubkey,
+            crate::instruction::AccessSlots {}.data(),
+            accounts.clone(),
+        )
+        .await;
+        send_instruction(
+            &mut context,
+            crate::instruction::ValidateList { layout }.data(),
+            accounts.clone(),
         )
         .await;
     }
@@ -0,0 +1,111 @@
+//! Drives the ring buffer and the list with the same operations through the [`Queue`] trait,
+//! asserting they behave as the same FIFO queue.
+//!
+//! Both backends implement the same semantics, removals at arbitrary positions included, so
+//! a run stops at the first difference and fails the test with both states.
+
+use super::model::{op, Op};
+use super::{DLLEventQueue, EventQueue, Queue};
+use bytemuck::Zeroable;
+use proptest::prelude::*;
+
+/// Observable state of a queue after an operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Item pushed, popped or removed by the operation, or number of items visited.
+    pub outcome: Option<u64>,
+    pub seq_num: u64,
+    pub items: Vec<u64>,
+}
+
+/// First operation after which the backends disagree, which is a bug in one of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub step: usize,
+    pub op: Op,
+    pub ring: Snapshot,
+    pub list: Snapshot,
+}
+
+fn apply(queue: &mut impl Queue<Item = u64>, op: Op) -> Snapshot {
+    let outcome = match op {
+        Op::Push => {
+            let item = queue.seq_num();
+            queue.push_back(item).ok().map(|()| item)
+        }
+        Op::Pop => queue.pop_front().ok(),
+        Op::PopBack => queue.pop_back().ok(),
+        Op::RemoveAt(index) => queue.remove_at(index).ok(),
+        Op::Iterate(n) => Some(queue.iter().take(n).count() as u64),
+        Op::DeleteSlot(_) | Op::RevertPushes(_) => unreachable!("not a queue operation"),
+    };
+    Snapshot {
+        outcome,
+        seq_num: queue.seq_num(),
+        items: queue.iter().copied().collect(),
+    }
+}
+
+/// Runs `ops` on both backends of capacity `N`, returning the first difference between them.
+pub fn first_divergence<const N: usize>(ops: &[Op]) -> Option<Divergence> {
+    let mut ring = EventQueue::<N, u64>::zeroed();
+    let mut list = DLLEventQueue::<N, u16, u64>::zeroed();
+    Queue::init(&mut ring);
+    list.init();
+
+    for (step, &op) in ops.iter().enumerate() {
+        let ring_snapshot = apply(&mut ring, op);
+        let list_snapshot = apply(&mut list, op);
+        if ring_snapshot != list_snapshot {
+            return Some(Divergence {
+                step,
+                op,
+                ring: ring_snapshot,
+                list: list_snapshot,
+            });
+        }
+    }
+    None
+}
+
+/// Operations of the [`Queue`] trait, shared by both backends.
+fn queue_op(capacity: usize) -> impl Strategy<Value = Op> {
+    op(capacity).prop_filter("backend specific", |op| {
+        !matches!(op, Op::DeleteSlot(_) | Op::RevertPushes(_))
+    })
+}
+
+#[test]
+fn fifo_operations_do_not_diverge() {
+    let mut ops = vec![Op::Push; 5];
+    ops.extend([
+        Op::Pop,
+        Op::Iterate(3),
+        Op::PopBack,
+        Op::Push,
+        Op::Push,
+        Op::Push,
+    ]);
+    ops.extend([Op::Pop; 6]);
+    assert_eq!(first_divergence::<5>(&ops), None);
+}
+
+#[test]
+fn removals_keep_the_same_order() {
+    let mut ops = vec![Op::Push; 4];
+    ops.extend([Op::RemoveAt(0), Op::RemoveAt(1), Op::Push, Op::RemoveAt(2)]);
+    ops.extend([Op::Push, Op::Push, Op::RemoveAt(1), Op::RemoveAt(2)]);
+    assert_eq!(first_divergence::<4>(&ops), None);
+}
+
+proptest! {
+    #[test]
+    fn backends_agree(ops in prop::collection::vec(queue_op(8), 0..200)) {
+        prop_assert_eq!(first_divergence::<8>(&ops), None);
+    }
+
+    #[test]
+    fn large_backends_agree(ops in prop::collection::vec(queue_op(64), 0..400)) {
+        prop_assert_eq!(first_divergence::<64>(&ops), None);
+    }
+}
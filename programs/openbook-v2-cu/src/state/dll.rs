@@ -1,40 +1,108 @@
-use super::MAX_NUM_EVENTS;
+use super::{Queue, QueueAccount, QueueItem, MAX_NUM_EVENTS};
+use crate::error::{PushError, QueueError};
 use anchor_lang::prelude::*;
-use openbook_v2::{error::OpenBookError, state::AnyEvent};
+use bytemuck::{Pod, Zeroable};
+use openbook_v2::state::AnyEvent;
 use static_assertions::const_assert_eq;
+use std::{fmt::Debug, mem::size_of};
+
+/// Integer type of the `next`/`prev` links between nodes. Its maximum value is reserved as
+/// the `NULL` link, so a queue can hold at most `MAX_CAPACITY` events.
+///
+/// Sealed, as the `Pod` impls of [`Node`] and [`DLLHeader`] rely on `Padding` being right.
+pub trait SlotIndex: sealed::Sealed + Pod + Debug + Eq {
+    const NULL: Self;
+    const MAX_CAPACITY: usize;
+    /// Pads the two links of a [`Node`] to 8 bytes, keeping its item aligned.
+    type Padding: Pod + Debug;
+
+    fn from_usize(value: usize) -> Self;
+    fn to_usize(self) -> usize;
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for u16 {}
+    impl Sealed for u32 {}
+}
+
+impl SlotIndex for u16 {
+    const NULL: Self = u16::MAX;
+    const MAX_CAPACITY: usize = u16::MAX as usize;
+    type Padding = [u8; 4];
+
+    fn from_usize(value: usize) -> Self {
+        value as u16
+    }
+
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+}
+
+impl SlotIndex for u32 {
+    const NULL: Self = u32::MAX;
+    const MAX_CAPACITY: usize = u32::MAX as usize;
+    type Padding = [u8; 0];
+
+    fn from_usize(value: usize) -> Self {
+        value as u32
+    }
 
-pub const NULL: u16 = u16::MAX;
-pub const LAST_SLOT: usize = MAX_NUM_EVENTS - 1;
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+}
 
-#[account(zero_copy)]
-pub struct DLLEventQueue {
-    pub header: DLLHeader,
-    pub nodes: [Node; MAX_NUM_EVENTS],
+#[account(zero_copy(unsafe))]
+#[repr(C)]
+pub struct DLLEventQueue<
+    const N: usize = MAX_NUM_EVENTS,
+    I: SlotIndex = u16,
+    T: QueueItem = AnyEvent,
+> {
+    pub header: DLLHeader<I>,
+    pub nodes: [Node<I, T>; N],
     pub reserved: [u8; 64],
 }
-const_assert_eq!(std::mem::size_of::<DLLEventQueue>(), 16 + 488 * 208 + 64);
-const_assert_eq!(std::mem::size_of::<DLLEventQueue>(), 101584);
-const_assert_eq!(std::mem::size_of::<DLLEventQueue>() % 8, 0);
+const_assert_eq!(size_of::<DLLEventQueue>(), <DLLEventQueue>::SIZE);
+const_assert_eq!(
+    size_of::<DLLEventQueue<64, u32, [u8; 32]>>(),
+    DLLEventQueue::<64, u32, [u8; 32]>::SIZE
+);
+
+impl<const N: usize, I: SlotIndex, T: QueueItem> QueueAccount for DLLEventQueue<N, I, T> {
+    // summing the fields of the nodes rather than taking their size, which would hide the
+    // padding of a node after its event, e.g. holding a `[u8; 33]`
+    const SIZE: usize = size_of::<DLLHeader<I>>()
+        + N * (2 * size_of::<I>() + size_of::<I::Padding>() + size_of::<T>())
+        + 64;
+    const CAPACITY: usize = N;
+    const MAX_CAPACITY: usize = I::MAX_CAPACITY;
+}
 
-impl DLLEventQueue {
+impl<const N: usize, I: SlotIndex, T: QueueItem> DLLEventQueue<N, I, T> {
     pub fn init(&mut self) {
+        Self::check_layout();
+
         self.header = DLLHeader {
-            free_head: 0,
-            used_head: NULL,
-            count: 0,
+            free_head: I::from_usize(0),
+            used_head: I::NULL,
+            count: I::from_usize(0),
             seq_num: 0,
-            _padd: Default::default(),
+            _padd: I::zeroed(),
         };
 
-        for i in 0..MAX_NUM_EVENTS {
+        for i in 0..N {
             self.nodes[i].set_next(i + 1);
-            self.nodes[i].set_prev(NULL as usize);
+            self.nodes[i].prev = I::NULL;
         }
-        self.nodes[LAST_SLOT].set_next(NULL as usize);
+        self.nodes[N - 1].next = I::NULL;
     }
 
     pub fn len(&self) -> usize {
-        self.header.count() as usize
+        self.header.count()
     }
 
     pub fn is_empty(&self) -> bool {
@@ -45,10 +113,17 @@ impl DLLEventQueue {
         self.len() == self.nodes.len()
     }
 
-    pub fn push_back(&mut self, value: AnyEvent) {
-        assert!(!self.is_full());
+    /// Appends an event, handing it back if the queue is full or its header or links point
+    /// out of range.
+    pub fn push_back(&mut self, value: T) -> std::result::Result<(), PushError<T>> {
+        if self.is_full() {
+            return Err(PushError::Full(value));
+        }
 
         let slot = self.header.free_head();
+        if self.len() > N || slot >= N || self.header.seq_num == u64::MAX {
+            return Err(PushError::Corrupted(value));
+        }
         let new_next: usize;
         let new_prev: usize;
 
@@ -56,15 +131,18 @@ impl DLLEventQueue {
             new_next = slot;
             new_prev = slot;
 
-            self.header.set_free_head(self.nodes[slot].next() as u16);
-            self.header.set_used_head(slot as u16);
+            self.header.free_head = self.nodes[slot].next;
+            self.header.set_used_head(slot);
         } else {
             new_next = self.header.used_head();
-            new_prev = self.nodes[new_next].prev as usize;
+            new_prev = match self.nodes.get(new_next) {
+                Some(node) if node.prev() < N => node.prev(),
+                _ => return Err(PushError::Corrupted(value)),
+            };
 
             self.nodes[new_prev].set_next(slot);
             self.nodes[new_next].set_prev(slot);
-            self.header.set_free_head(self.nodes[slot].next() as u16);
+            self.header.free_head = self.nodes[slot].next;
         }
 
         self.header.incr_count();
@@ -72,129 +150,447 @@ impl DLLEventQueue {
         self.nodes[slot].event = value;
         self.nodes[slot].set_next(new_next);
         self.nodes[slot].set_prev(new_prev);
+        Ok(())
     }
 
-    pub fn front(&self) -> Option<&AnyEvent> {
+    pub fn front(&self) -> Option<&T> {
         if self.is_empty() {
             return None;
         } else {
-            Some(&self.nodes[self.header.used_head()].event)
+            self.nodes
+                .get(self.header.used_head())
+                .map(|node| &node.event)
         }
     }
 
-    pub fn at(&self, slot: usize) -> Option<&AnyEvent> {
-        if self.nodes[slot].is_free() {
+    pub fn back(&self) -> Option<&T> {
+        if self.is_empty() {
             return None;
-        } else {
-            Some(&self.nodes[slot].event)
         }
+        let back_slot = self.nodes.get(self.header.used_head())?.prev();
+        self.nodes.get(back_slot).map(|node| &node.event)
+    }
+
+    /// Event stored in `slot`, failing if the slot is out of range or holds no event.
+    pub fn at(&self, slot: usize) -> Result<&T> {
+        let node = self.nodes.get(slot).ok_or(QueueError::SlotOutOfRange)?;
+        require!(!node.is_free(), QueueError::SlotFree);
+        Ok(&node.event)
+    }
+
+    /// Event stored in `slot`, without any check.
+    ///
+    /// # Safety
+    ///
+    /// `slot` must be below the capacity and hold an event, e.g. a slot yielded by
+    /// [`Self::iter`] with no deletion since.
+    pub unsafe fn at_unchecked(&self, slot: usize) -> &T {
+        &self.nodes.get_unchecked(slot).event
     }
 
-    pub fn delete(&mut self) -> Result<AnyEvent> {
+    pub fn delete(&mut self) -> Result<T> {
+        require!(!self.is_empty(), QueueError::QueueEmpty);
         self.delete_slot(self.header.used_head())
     }
 
-    pub fn delete_slot(&mut self, slot: usize) -> Result<AnyEvent> {
-        if self.is_empty() || self.nodes[slot].is_free() {
-            return Err(OpenBookError::SomeError.into());
-        }
+    /// Deletes the last event, leaving `seq_num` unchanged.
+    pub fn pop_back(&mut self) -> Result<T> {
+        require!(!self.is_empty(), QueueError::QueueEmpty);
+        let back_slot = self
+            .nodes
+            .get(self.header.used_head())
+            .ok_or(QueueError::CorruptedQueue)?
+            .prev();
+        self.delete_slot(back_slot)
+    }
+
+    pub fn delete_slot(&mut self, slot: usize) -> Result<T> {
+        require!(slot < N, QueueError::SlotOutOfRange);
+        require!(!self.is_empty(), QueueError::QueueEmpty);
+        require!(!self.nodes[slot].is_free(), QueueError::SlotAlreadyFree);
 
         let prev_slot = self.nodes[slot].prev();
         let next_slot = self.nodes[slot].next();
-        let next_free = self.header.free_head();
+        let next_free = self.header.free_head;
+        require!(prev_slot < N && next_slot < N, QueueError::CorruptedQueue);
 
         self.nodes[prev_slot].set_next(next_slot);
         self.nodes[next_slot].set_prev(prev_slot);
 
-        self.header.set_free_head(slot as u16);
+        self.header.set_free_head(slot);
 
         if self.header.count() == 1 {
-            self.header.set_used_head(NULL);
+            self.header.used_head = I::NULL;
         } else if self.header.used_head() == slot {
-            self.header.set_used_head(next_slot as u16);
+            self.header.set_used_head(next_slot);
         };
 
         self.header.decr_count();
-        self.nodes[slot].set_next(next_free);
-        self.nodes[slot].set_prev(NULL as usize);
+        self.nodes[slot].next = next_free;
+        self.nodes[slot].prev = I::NULL;
 
         Ok(self.nodes[slot].event)
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = EventWithSlot> {
+    /// Slot holding the event at `index` in FIFO order, walking from the closer end.
+    fn slot_at(&self, index: usize) -> Option<usize> {
+        if index >= self.len() {
+            return None;
+        }
+        let mut slot = self.header.used_head();
+        if index <= self.len() / 2 {
+            for _ in 0..index {
+                slot = self.nodes.get(slot)?.next();
+            }
+        } else {
+            for _ in index..self.len() {
+                slot = self.nodes.get(slot)?.prev();
+            }
+        }
+        Some(slot)
+    }
+
+    pub fn iter(&self) -> DLLEventQueueIterator<'_, N, I, T> {
+        // clamped like the links followed afterwards, and a corrupted count cannot ask for
+        // more events than there are slots
+        let used_head = self.header.used_head().min(N - 1);
         DLLEventQueueIterator {
             queue: self,
-            slot: self.header.used_head(),
+            slot: used_head,
             index: 0,
+            back_slot: self.nodes[used_head].prev().min(N - 1),
+            back: self.len().min(N),
+        }
+    }
+
+    /// Walks the used ring and the free list, checking that every slot is linked into exactly
+    /// one of them and that the links agree with the header.
+    ///
+    /// Only reads the queue, so it can check a queue loaded from any account data, e.g. with
+    /// `bytemuck::pod_read_unaligned(&data[8..])` off chain.
+    pub fn validate(&self) -> std::result::Result<(), Corruption> {
+        const UNSEEN: u8 = 0;
+        const USED: u8 = 1;
+        const FREE: u8 = 2;
+        let mut seen = vec![UNSEEN; N];
+
+        let count = self.header.count();
+        if count > N {
+            return Err(Corruption::CountTooLarge { count });
+        }
+
+        let used_head = self.header.used_head();
+        if count == 0 {
+            if self.header.used_head != I::NULL {
+                return Err(Corruption::UsedHeadOfEmptyQueue { slot: used_head });
+            }
+        } else {
+            let mut slot = used_head;
+            if slot >= N {
+                return Err(Corruption::LinkOutOfRange {
+                    from: None,
+                    to: slot,
+                });
+            }
+            for index in 0..count {
+                if seen[slot] != UNSEEN {
+                    return Err(Corruption::UsedSlotRevisited { slot, index });
+                }
+                seen[slot] = USED;
+
+                let next = self.nodes[slot].next();
+                if next >= N {
+                    return Err(Corruption::LinkOutOfRange {
+                        from: Some(slot),
+                        to: next,
+                    });
+                }
+                if self.nodes[next].prev() != slot {
+                    return Err(Corruption::AsymmetricLinks {
+                        slot,
+                        next,
+                        prev_of_next: self.nodes[next].prev(),
+                    });
+                }
+                slot = next;
+            }
+            if slot != used_head {
+                return Err(Corruption::UsedRingNotClosed { count, next: slot });
+            }
+        }
+
+        let mut from = None;
+        let mut slot = self.header.free_head();
+        let mut free = 0;
+        while slot != I::NULL.to_usize() {
+            if slot >= N {
+                return Err(Corruption::LinkOutOfRange { from, to: slot });
+            }
+            match seen[slot] {
+                USED => return Err(Corruption::SlotInBothLists { slot }),
+                FREE => return Err(Corruption::FreeListCycle { slot }),
+                _ => seen[slot] = FREE,
+            }
+            if !self.nodes[slot].is_free() {
+                return Err(Corruption::FreeSlotWithPrev { slot });
+            }
+            free += 1;
+            from = Some(slot);
+            slot = self.nodes[slot].next();
+        }
+
+        match seen.iter().position(|&s| s == UNSEEN) {
+            Some(slot) => Err(Corruption::SlotInNoList { slot }),
+            None => {
+                debug_assert_eq!(count + free, N);
+                Ok(())
+            }
         }
     }
 }
 
-pub struct EventWithSlot<'a> {
-    event: &'a AnyEvent,
-    slot: usize,
+impl<const N: usize, I: SlotIndex, T: QueueItem> Queue for DLLEventQueue<N, I, T> {
+    type Item = T;
+    type Iter<'a> =
+        std::iter::Map<DLLEventQueueIterator<'a, N, I, T>, fn(EventWithSlot<'a, T>) -> &'a T>;
+
+    fn init(&mut self) {
+        self.init();
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn seq_num(&self) -> u64 {
+        self.header.seq_num
+    }
+
+    fn push_back(&mut self, value: T) -> std::result::Result<(), PushError<T>> {
+        self.push_back(value)
+    }
+
+    fn pop_front(&mut self) -> Result<T> {
+        self.delete()
+    }
+
+    fn peek_front(&self) -> Option<&T> {
+        self.front()
+    }
+
+    fn pop_back(&mut self) -> Result<T> {
+        self.pop_back()
+    }
+
+    fn peek_back(&self) -> Option<&T> {
+        self.back()
+    }
+
+    fn remove_at(&mut self, index: usize) -> Result<T> {
+        let slot = self.slot_at(index).ok_or(QueueError::IndexOutOfRange)?;
+        self.delete_slot(slot)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter().map(|e| e.event)
+    }
+}
+
+/// Inconsistency found by [`DLLEventQueue::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corruption {
+    /// The header counts more events than the queue can hold.
+    CountTooLarge { count: usize },
+    /// The queue is empty but its used head is not `NULL`.
+    UsedHeadOfEmptyQueue { slot: usize },
+    /// A link points past the last slot, `from` being `None` for the header.
+    LinkOutOfRange { from: Option<usize>, to: usize },
+    /// The `prev` link of `next` does not point back to `slot`.
+    AsymmetricLinks {
+        slot: usize,
+        next: usize,
+        prev_of_next: usize,
+    },
+    /// The used ring comes back to `slot` after `index` events, before reaching the count.
+    UsedSlotRevisited { slot: usize, index: usize },
+    /// The used ring does not come back to its head after `count` events.
+    UsedRingNotClosed { count: usize, next: usize },
+    /// The free list reaches a slot of the used ring.
+    SlotInBothLists { slot: usize },
+    /// The free list comes back to `slot` instead of ending on `NULL`.
+    FreeListCycle { slot: usize },
+    /// A slot of the free list has a `prev` link, so it looks used.
+    FreeSlotWithPrev { slot: usize },
+    /// The slot is reachable from neither the used head nor the free head.
+    SlotInNoList { slot: usize },
+}
+
+impl std::fmt::Display for Corruption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::CountTooLarge { count } => write!(f, "count {} exceeds the capacity", count),
+            Self::UsedHeadOfEmptyQueue { slot } => {
+                write!(f, "empty queue has used head {}", slot)
+            }
+            Self::LinkOutOfRange { from: None, to } => {
+                write!(f, "header links to slot {} out of range", to)
+            }
+            Self::LinkOutOfRange {
+                from: Some(from),
+                to,
+            } => {
+                write!(f, "slot {} links to slot {} out of range", from, to)
+            }
+            Self::AsymmetricLinks {
+                slot,
+                next,
+                prev_of_next,
+            } => write!(
+                f,
+                "slot {} links to {} whose prev is {}",
+                slot, next, prev_of_next
+            ),
+            Self::UsedSlotRevisited { slot, index } => {
+                write!(f, "used ring revisits slot {} after {} events", slot, index)
+            }
+            Self::UsedRingNotClosed { count, next } => write!(
+                f,
+                "used ring goes on to slot {} after {} events instead of closing",
+                next, count
+            ),
+            Self::SlotInBothLists { slot } => {
+                write!(
+                    f,
+                    "slot {} is in both the used ring and the free list",
+                    slot
+                )
+            }
+            Self::FreeListCycle { slot } => write!(f, "free list loops back to slot {}", slot),
+            Self::FreeSlotWithPrev { slot } => write!(f, "free slot {} has a prev link", slot),
+            Self::SlotInNoList { slot } => write!(f, "slot {} is in no list", slot),
+        }
+    }
+}
+
+impl std::error::Error for Corruption {}
+
+pub struct EventWithSlot<'a, T = AnyEvent> {
+    pub event: &'a T,
+    pub slot: usize,
 }
 
-struct DLLEventQueueIterator<'a> {
-    queue: &'a DLLEventQueue,
+/// Walks the used ring from both ends, following `next` links from the front and `prev`
+/// links from the back.
+///
+/// Links out of range are clamped to the last slot, so that on corrupted links it still
+/// yields as many events as the queue holds, if not the right ones.
+pub struct DLLEventQueueIterator<'a, const N: usize, I: SlotIndex, T: QueueItem> {
+    queue: &'a DLLEventQueue<N, I, T>,
+    /// Slot of the next event from the front.
     slot: usize,
+    /// Index of the next event from the front.
     index: usize,
+    /// Slot of the next event from the back.
+    back_slot: usize,
+    /// Index past the next event from the back.
+    back: usize,
 }
 
-impl<'a> Iterator for DLLEventQueueIterator<'a> {
-    type Item = EventWithSlot<'a>;
+impl<'a, const N: usize, I: SlotIndex, T: QueueItem> Iterator
+    for DLLEventQueueIterator<'a, N, I, T>
+{
+    type Item = EventWithSlot<'a, T>;
     fn next(&mut self) -> Option<Self::Item> {
-        if self.index == self.queue.len() {
+        if self.index == self.back {
             None
         } else {
             let slot = self.slot;
-            let item = &self.queue.nodes[slot].event;
-            self.slot = self.queue.nodes[slot].next();
+            let node = &self.queue.nodes[slot];
+            self.slot = node.next().min(N - 1);
             self.index += 1;
-            Some(EventWithSlot { event: item, slot })
+            Some(EventWithSlot {
+                event: &node.event,
+                slot,
+            })
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.index;
+        (len, Some(len))
+    }
+}
+
+impl<'a, const N: usize, I: SlotIndex, T: QueueItem> DoubleEndedIterator
+    for DLLEventQueueIterator<'a, N, I, T>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.index == self.back {
+            None
+        } else {
+            let slot = self.back_slot;
+            let node = &self.queue.nodes[slot];
+            self.back_slot = node.prev().min(N - 1);
+            self.back -= 1;
+            Some(EventWithSlot {
+                event: &node.event,
+                slot,
+            })
         }
     }
 }
 
-#[zero_copy]
+impl<'a, const N: usize, I: SlotIndex, T: QueueItem> ExactSizeIterator
+    for DLLEventQueueIterator<'a, N, I, T>
+{
+}
+
+#[zero_copy(unsafe)]
+#[repr(C)]
 #[derive(Debug)]
-pub struct DLLHeader {
-    free_head: u16,
-    used_head: u16,
-    count: u16,
-    _padd: u16,
+pub struct DLLHeader<I: SlotIndex = u16> {
+    free_head: I,
+    used_head: I,
+    count: I,
+    _padd: I,
     pub seq_num: u64,
 }
-const_assert_eq!(std::mem::size_of::<DLLHeader>(), 16);
-const_assert_eq!(std::mem::size_of::<DLLHeader>() % 8, 0);
+const_assert_eq!(size_of::<DLLHeader<u16>>(), 4 * size_of::<u16>() + 8);
+const_assert_eq!(size_of::<DLLHeader<u32>>(), 4 * size_of::<u32>() + 8);
+unsafe impl<I: SlotIndex> Pod for DLLHeader<I> {}
+unsafe impl<I: SlotIndex> Zeroable for DLLHeader<I> {}
 
-impl DLLHeader {
+impl<I: SlotIndex> DLLHeader<I> {
     pub fn count(&self) -> usize {
-        self.count as usize
+        self.count.to_usize()
     }
 
     pub fn free_head(&self) -> usize {
-        self.free_head as usize
+        self.free_head.to_usize()
     }
 
     pub fn used_head(&self) -> usize {
-        self.used_head as usize
+        self.used_head.to_usize()
     }
 
-    fn set_free_head(&mut self, value: u16) {
-        self.free_head = value;
+    fn set_free_head(&mut self, slot: usize) {
+        self.free_head = I::from_usize(slot);
     }
 
-    fn set_used_head(&mut self, value: u16) {
-        self.used_head = value;
+    fn set_used_head(&mut self, slot: usize) {
+        self.used_head = I::from_usize(slot);
     }
 
     fn incr_count(&mut self) {
-        self.count += 1;
+        self.count = I::from_usize(self.count() + 1);
     }
 
     fn decr_count(&mut self) {
-        self.count -= 1;
+        self.count = I::from_usize(self.count() - 1);
     }
 
     fn incr_event_id(&mut self) {
@@ -202,36 +598,51 @@ impl DLLHeader {
     }
 }
 
-#[zero_copy]
+#[zero_copy(unsafe)]
+#[repr(C)]
 #[derive(Debug)]
-pub struct Node {
-    next: u16,
-    prev: u16,
-    _pad: [u8; 4],
-    pub event: AnyEvent,
+pub struct Node<I: SlotIndex = u16, T: QueueItem = AnyEvent> {
+    next: I,
+    prev: I,
+    _pad: I::Padding,
+    pub event: T,
 }
-const_assert_eq!(std::mem::size_of::<Node>(), 8 + 200);
-const_assert_eq!(std::mem::size_of::<Node>() % 8, 0);
-
-impl Node {
+const_assert_eq!(
+    size_of::<Node<u16>>(),
+    2 * size_of::<u16>() + size_of::<<u16 as SlotIndex>::Padding>() + size_of::<AnyEvent>()
+);
+const_assert_eq!(
+    size_of::<Node<u32>>(),
+    2 * size_of::<u32>() + size_of::<AnyEvent>()
+);
+const_assert_eq!(
+    size_of::<Node<u16, [u8; 32]>>(),
+    2 * size_of::<u16>() + size_of::<<u16 as SlotIndex>::Padding>() + size_of::<[u8; 32]>()
+);
+// no padding: the links and their padding take 8 bytes, the alignment a `QueueItem` needs at
+// most, and the item is a multiple of 8 bytes itself
+unsafe impl<I: SlotIndex, T: QueueItem> Pod for Node<I, T> {}
+unsafe impl<I: SlotIndex, T: QueueItem> Zeroable for Node<I, T> {}
+
+impl<I: SlotIndex, T: QueueItem> Node<I, T> {
     pub fn is_free(&self) -> bool {
-        self.prev == NULL
+        self.prev == I::NULL
     }
 
     pub fn next(&self) -> usize {
-        self.next as usize
+        self.next.to_usize()
     }
 
     pub fn prev(&self) -> usize {
-        self.prev as usize
+        self.prev.to_usize()
     }
 
     fn set_next(&mut self, next: usize) {
-        self.next = next as u16;
+        self.next = I::from_usize(next);
     }
 
     fn set_prev(&mut self, prev: usize) {
-        self.prev = prev as u16;
+        self.prev = I::from_usize(prev);
     }
 }
 
@@ -240,6 +651,7 @@ mod test_event_queue {
     use super::*;
     use bytemuck::Zeroable;
 
+    const NULL: usize = u16::NULL as usize;
     const LAST_SLOT: usize = MAX_NUM_EVENTS - 1;
 
     fn count_free_nodes(event_queue: &DLLEventQueue) -> usize {
@@ -248,40 +660,50 @@ mod test_event_queue {
 
     #[test]
     fn init() {
-        let mut eq = DLLEventQueue::zeroed();
+        let mut eq = <DLLEventQueue>::zeroed();
         eq.init();
 
         assert_eq!(eq.header.count(), 0);
         assert_eq!(eq.header.free_head(), 0);
-        assert_eq!(eq.header.used_head(), NULL as usize);
+        assert_eq!(eq.header.used_head(), NULL);
         assert_eq!(count_free_nodes(&eq), MAX_NUM_EVENTS as usize);
     }
 
     #[test]
-    #[should_panic]
     fn cannot_insert_if_full() {
-        let mut eq = DLLEventQueue::zeroed();
+        let mut eq = <DLLEventQueue>::zeroed();
         eq.init();
-        for _ in 0..MAX_NUM_EVENTS + 1 {
-            eq.push_back(AnyEvent::zeroed());
+        for _ in 0..MAX_NUM_EVENTS {
+            eq.push_back(AnyEvent::zeroed()).unwrap();
         }
+
+        let mut rejected = AnyEvent::zeroed();
+        rejected.event_type = 7;
+        assert!(matches!(
+            eq.push_back(rejected),
+            Err(PushError::Full(event)) if event.event_type == 7
+        ));
+        assert_eq!(eq.header.count(), MAX_NUM_EVENTS);
+        assert_eq!(eq.header.seq_num, MAX_NUM_EVENTS as u64);
+        assert_eq!(eq.header.free_head(), NULL);
+        assert!(eq.iter().all(|e| e.event.event_type == 0));
     }
 
     #[test]
     #[should_panic]
     fn cannot_delete_if_empty() {
-        let mut eq = DLLEventQueue::zeroed();
+        let mut eq = <DLLEventQueue>::zeroed();
         eq.init();
         eq.delete().unwrap();
     }
 
     #[test]
     fn insert_until_full() {
-        let mut eq = DLLEventQueue::zeroed();
+        let mut eq = <DLLEventQueue>::zeroed();
         eq.init();
 
         // insert one event in the first slot; the single used node should point to himself
-        eq.push_back(AnyEvent::zeroed());
+        eq.push_back(AnyEvent::zeroed()).unwrap();
         assert_eq!(eq.header.used_head(), 0);
         assert_eq!(eq.header.free_head(), 1);
         assert_eq!(eq.nodes[0].prev(), 0);
@@ -289,7 +711,7 @@ mod test_event_queue {
         assert_eq!(eq.nodes[1].next(), 2);
 
         for i in 1..MAX_NUM_EVENTS - 2 {
-            eq.push_back(AnyEvent::zeroed());
+            eq.push_back(AnyEvent::zeroed()).unwrap();
             assert_eq!(eq.header.used_head(), 0);
             assert_eq!(eq.header.free_head(), i + 1);
             assert_eq!(eq.nodes[0].prev(), i);
@@ -298,33 +720,33 @@ mod test_event_queue {
         }
 
         // insert another one, afterwards only one free node pointing to himself should be left
-        eq.push_back(AnyEvent::zeroed());
+        eq.push_back(AnyEvent::zeroed()).unwrap();
         assert_eq!(eq.header.used_head(), 0);
         assert_eq!(eq.header.free_head(), LAST_SLOT);
         assert_eq!(eq.nodes[0].prev(), LAST_SLOT - 1);
         assert_eq!(eq.nodes[0].next(), 1);
-        assert_eq!(eq.nodes[LAST_SLOT].next(), NULL as usize);
+        assert_eq!(eq.nodes[LAST_SLOT].next(), NULL);
 
         // insert last available event
-        eq.push_back(AnyEvent::zeroed());
+        eq.push_back(AnyEvent::zeroed()).unwrap();
         assert_eq!(eq.header.used_head(), 0);
-        assert_eq!(eq.header.free_head(), NULL as usize);
+        assert_eq!(eq.header.free_head(), NULL);
         assert_eq!(eq.nodes[0].prev(), LAST_SLOT);
         assert_eq!(eq.nodes[0].next(), 1);
     }
 
     #[test]
     fn delete_full() {
-        let mut eq = DLLEventQueue::zeroed();
+        let mut eq = <DLLEventQueue>::zeroed();
         eq.init();
         for _ in 0..MAX_NUM_EVENTS {
-            eq.push_back(AnyEvent::zeroed());
+            eq.push_back(AnyEvent::zeroed()).unwrap();
         }
 
         eq.delete().unwrap();
         assert_eq!(eq.header.free_head(), 0);
         assert_eq!(eq.header.used_head(), 1);
-        assert_eq!(eq.nodes[0].next(), NULL as usize);
+        assert_eq!(eq.nodes[0].next(), NULL);
         assert_eq!(eq.nodes[1].prev(), LAST_SLOT);
         assert_eq!(eq.nodes[1].next(), 2);
 
@@ -345,7 +767,7 @@ mod test_event_queue {
         assert_eq!(eq.nodes[LAST_SLOT].next(), LAST_SLOT);
 
         eq.delete().unwrap();
-        assert_eq!(eq.header.used_head(), NULL as usize);
+        assert_eq!(eq.header.used_head(), NULL);
         assert_eq!(eq.header.free_head(), LAST_SLOT);
         assert_eq!(eq.nodes[LAST_SLOT].next(), LAST_SLOT - 1);
 
@@ -358,28 +780,205 @@ mod test_event_queue {
 
     #[test]
     fn delete_at_given_position() {
-        let mut eq = DLLEventQueue::zeroed();
+        let mut eq = <DLLEventQueue>::zeroed();
         eq.init();
         for _ in 0..5 {
-            eq.push_back(AnyEvent::zeroed());
+            eq.push_back(AnyEvent::zeroed()).unwrap();
         }
         eq.delete_slot(2).unwrap();
         assert_eq!(eq.header.free_head(), 2);
         assert_eq!(eq.header.used_head(), 0);
     }
 
+    #[test]
+    fn validate_accepts_consistent_queues() {
+        let mut eq = <DLLEventQueue>::zeroed();
+        eq.init();
+        eq.validate().unwrap();
+        for _ in 0..MAX_NUM_EVENTS {
+            eq.push_back(AnyEvent::zeroed()).unwrap();
+        }
+        eq.validate().unwrap();
+        for slot in [7, 0, LAST_SLOT, 100] {
+            eq.delete_slot(slot).unwrap();
+            eq.validate().unwrap();
+        }
+        while !eq.is_empty() {
+            eq.delete().unwrap();
+        }
+        eq.validate().unwrap();
+
+        let bytes = bytemuck::bytes_of(&eq);
+        let copy: DLLEventQueue = bytemuck::pod_read_unaligned(bytes);
+        copy.validate().unwrap();
+    }
+
+    #[test]
+    fn validate_reports_broken_links() {
+        let mut eq = <DLLEventQueue>::zeroed();
+        eq.init();
+        for _ in 0..5 {
+            eq.push_back(AnyEvent::zeroed()).unwrap();
+        }
+
+        let mut broken = eq;
+        broken.header.count = 6;
+        assert_eq!(
+            broken.validate(),
+            Err(Corruption::UsedSlotRevisited { slot: 0, index: 5 })
+        );
+
+        let mut broken = eq;
+        broken.header.count = 4;
+        assert_eq!(
+            broken.validate(),
+            Err(Corruption::UsedRingNotClosed { count: 4, next: 4 })
+        );
+
+        let mut broken = eq;
+        broken.nodes[3].set_prev(1);
+        assert_eq!(
+            broken.validate(),
+            Err(Corruption::AsymmetricLinks {
+                slot: 2,
+                next: 3,
+                prev_of_next: 1
+            })
+        );
+
+        let mut broken = eq;
+        broken.nodes[4].set_next(MAX_NUM_EVENTS);
+        assert_eq!(
+            broken.validate(),
+            Err(Corruption::LinkOutOfRange {
+                from: Some(4),
+                to: MAX_NUM_EVENTS
+            })
+        );
+
+        let mut broken = eq;
+        broken.header.set_free_head(2);
+        assert_eq!(
+            broken.validate(),
+            Err(Corruption::SlotInBothLists { slot: 2 })
+        );
+
+        let mut broken = eq;
+        broken.nodes[6].set_next(5);
+        assert_eq!(
+            broken.validate(),
+            Err(Corruption::FreeListCycle { slot: 5 })
+        );
+
+        let mut broken = eq;
+        broken.nodes[9].set_next(NULL);
+        assert_eq!(
+            broken.validate(),
+            Err(Corruption::SlotInNoList { slot: 10 })
+        );
+
+        let mut broken = eq;
+        broken.header.count = 0;
+        broken.header.set_used_head(NULL);
+        assert_eq!(broken.validate(), Err(Corruption::SlotInNoList { slot: 0 }));
+    }
+
     #[test]
     #[should_panic]
     fn cannot_delete_twice_same() {
-        let mut eq = DLLEventQueue::zeroed();
+        let mut eq = <DLLEventQueue>::zeroed();
         eq.init();
         for _ in 0..5 {
-            eq.push_back(AnyEvent::zeroed());
+            eq.push_back(AnyEvent::zeroed()).unwrap();
         }
         eq.delete_slot(2).unwrap();
         eq.delete_slot(2).unwrap();
     }
 
+    #[test]
+    fn at_checks_the_slot() {
+        let mut eq = <DLLEventQueue>::zeroed();
+        eq.init();
+        let mut event = AnyEvent::zeroed();
+        event.event_type = 3;
+        eq.push_back(event).unwrap();
+
+        assert_eq!(eq.at(0).unwrap().event_type, 3);
+        assert_eq!(unsafe { eq.at_unchecked(0) }.event_type, 3);
+        assert_eq!(eq.at(1).unwrap_err(), QueueError::SlotFree.into());
+        assert_eq!(eq.at(NULL).unwrap_err(), QueueError::SlotOutOfRange.into());
+        assert_eq!(
+            eq.at(MAX_NUM_EVENTS).unwrap_err(),
+            QueueError::SlotOutOfRange.into()
+        );
+    }
+
+    #[test]
+    fn delete_reports_why_it_failed() {
+        let mut eq = <DLLEventQueue>::zeroed();
+        eq.init();
+        assert_eq!(eq.delete().unwrap_err(), QueueError::QueueEmpty.into());
+        assert_eq!(
+            eq.delete_slot(0).unwrap_err(),
+            QueueError::QueueEmpty.into()
+        );
+
+        eq.push_back(AnyEvent::zeroed()).unwrap();
+        assert_eq!(
+            eq.delete_slot(1).unwrap_err(),
+            QueueError::SlotAlreadyFree.into()
+        );
+        assert_eq!(
+            eq.delete_slot(MAX_NUM_EVENTS).unwrap_err(),
+            QueueError::SlotOutOfRange.into()
+        );
+        assert_eq!(eq.len(), 1);
+    }
+
+    #[test]
+    fn survives_links_out_of_range() {
+        let mut eq = <DLLEventQueue>::zeroed();
+        eq.init();
+        for _ in 0..3 {
+            eq.push_back(AnyEvent::zeroed()).unwrap();
+        }
+        eq.nodes[1].set_next(NULL);
+        eq.nodes[1].set_prev(MAX_NUM_EVENTS);
+
+        assert_eq!(eq.iter().len(), 3);
+        assert_eq!(eq.iter().count(), 3);
+        assert_eq!(eq.iter().rev().count(), 3);
+        assert_eq!(
+            Queue::remove_at(&mut eq, 1).unwrap_err(),
+            QueueError::CorruptedQueue.into()
+        );
+        assert_eq!(
+            eq.delete_slot(1).unwrap_err(),
+            QueueError::CorruptedQueue.into()
+        );
+    }
+
+    #[test]
+    fn push_rejects_links_out_of_range() {
+        let mut eq = <DLLEventQueue>::zeroed();
+        eq.init();
+        eq.header.set_free_head(MAX_NUM_EVENTS);
+        assert!(matches!(
+            eq.push_back(AnyEvent::zeroed()),
+            Err(PushError::Corrupted(_))
+        ));
+
+        eq.init();
+        eq.push_back(AnyEvent::zeroed()).unwrap();
+        eq.nodes[0].set_prev(NULL);
+        assert!(matches!(
+            eq.push_back(AnyEvent::zeroed()),
+            Err(PushError::Corrupted(_))
+        ));
+        assert_eq!(eq.len(), 1);
+        assert_eq!(eq.header.seq_num, 1);
+    }
+
     #[test]
     fn fifo_event_processing() {
         let event_1 = {
@@ -407,18 +1006,18 @@ mod test_event_queue {
         // [3|2| | | ] insert
         // [3| | | | ] delete
 
-        let mut eq = DLLEventQueue::zeroed();
+        let mut eq = <DLLEventQueue>::zeroed();
         eq.init();
         assert_eq!(eq.nodes[0].is_free(), true);
         assert_eq!(eq.nodes[1].is_free(), true);
         assert_eq!(eq.nodes[2].is_free(), true);
 
-        eq.push_back(event_1);
+        eq.push_back(event_1).unwrap();
         assert_eq!(eq.nodes[0].event.event_type, 1);
         assert_eq!(eq.nodes[1].is_free(), true);
         assert_eq!(eq.nodes[2].is_free(), true);
 
-        eq.push_back(event_2);
+        eq.push_back(event_2).unwrap();
         assert_eq!(eq.nodes[0].event.event_type, 1);
         assert_eq!(eq.nodes[1].event.event_type, 2);
         assert_eq!(eq.nodes[2].is_free(), true);
@@ -428,7 +1027,7 @@ mod test_event_queue {
         assert_eq!(eq.nodes[1].event.event_type, 2);
         assert_eq!(eq.nodes[2].is_free(), true);
 
-        eq.push_back(event_3);
+        eq.push_back(event_3).unwrap();
         assert_eq!(eq.nodes[0].event.event_type, 3);
         assert_eq!(eq.nodes[1].event.event_type, 2);
         assert_eq!(eq.nodes[2].is_free(), true);
@@ -449,17 +1048,17 @@ mod test_event_queue {
         // [0| |1|2|3] insert
         // [ | |0|1|2] insert
 
-        let mut eq = DLLEventQueue::zeroed();
+        let mut eq = <DLLEventQueue>::zeroed();
 
         eq.init();
         assert_eq!(eq.header.free_head(), 0);
         assert_eq!(eq.nodes[0].next(), 1);
 
-        eq.push_back(AnyEvent::zeroed());
+        eq.push_back(AnyEvent::zeroed()).unwrap();
         assert_eq!(eq.header.free_head(), 1);
         assert_eq!(eq.nodes[1].next(), 2);
 
-        eq.push_back(AnyEvent::zeroed());
+        eq.push_back(AnyEvent::zeroed()).unwrap();
         assert_eq!(eq.header.free_head(), 2);
         assert_eq!(eq.nodes[2].next(), 3);
 
@@ -471,11 +1070,11 @@ mod test_event_queue {
         assert_eq!(eq.header.free_head(), 1);
         assert_eq!(eq.nodes[1].next(), 0);
 
-        eq.push_back(AnyEvent::zeroed());
+        eq.push_back(AnyEvent::zeroed()).unwrap();
         assert_eq!(eq.header.free_head(), 0);
         assert_eq!(eq.nodes[0].next(), 2);
 
-        eq.push_back(AnyEvent::zeroed());
+        eq.push_back(AnyEvent::zeroed()).unwrap();
         assert_eq!(eq.header.free_head(), 2);
         assert_eq!(eq.nodes[2].next(), 3);
     }
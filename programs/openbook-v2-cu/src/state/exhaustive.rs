@@ -0,0 +1,85 @@
+//! Enumerates every operation sequence up to a bounded depth on tiny lists, checking the
+//! free list and the used ring after each step, like the hand-drawn traces of the list tests
+//! but complete.
+//!
+//! The default depths keep `cargo test` fast. Deeper runs are ignored by default:
+//! `EXHAUSTIVE_DEPTH=9 cargo test exhaustive -- --ignored`.
+
+use super::model::{apply_list, Model, Op};
+use super::DLLEventQueue;
+use bytemuck::Zeroable;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Every operation on a list of `capacity` slots, plus a slot out of range.
+fn alphabet(capacity: usize) -> Vec<Op> {
+    let mut ops = vec![Op::Push, Op::Pop, Op::PopBack];
+    ops.extend((0..capacity).map(Op::RemoveAt));
+    ops.extend((0..=capacity).map(Op::DeleteSlot));
+    ops
+}
+
+/// Applies every sequence of `depth` operations from `alphabet` to `queue`, returning the
+/// number of sequences run. Panics with the failing sequence.
+fn explore<const N: usize>(
+    queue: &DLLEventQueue<N, u16, u64>,
+    model: &Model,
+    alphabet: &[Op],
+    depth: usize,
+    trace: &mut Vec<Op>,
+) -> usize {
+    if depth == 0 {
+        return 1;
+    }
+    let mut sequences = 0;
+    for &op in alphabet {
+        let mut queue = *queue;
+        let mut model = model.clone();
+        trace.push(op);
+        if catch_unwind(AssertUnwindSafe(|| apply_list(&mut queue, &mut model, op))).is_err() {
+            panic!("invariant broken by {:?}", trace);
+        }
+        sequences += explore(&queue, &model, alphabet, depth - 1, trace);
+        trace.pop();
+    }
+    sequences
+}
+
+fn exhaustive<const N: usize>(depth: usize) {
+    let mut queue = DLLEventQueue::<N, u16, u64>::zeroed();
+    queue.init();
+    let alphabet = alphabet(N);
+    let sequences = explore(&queue, &Model::new(N), &alphabet, depth, &mut Vec::new());
+    assert_eq!(sequences, alphabet.len().pow(depth as u32));
+}
+
+#[test]
+fn exhaustive_capacity_2() {
+    exhaustive::<2>(7);
+}
+
+#[test]
+fn exhaustive_capacity_3() {
+    exhaustive::<3>(6);
+}
+
+#[test]
+fn exhaustive_capacity_4() {
+    exhaustive::<4>(5);
+}
+
+#[test]
+fn exhaustive_capacity_5() {
+    exhaustive::<5>(5);
+}
+
+#[test]
+#[ignore = "slow, set EXHAUSTIVE_DEPTH to go deeper"]
+fn exhaustive_deep() {
+    let depth = std::env::var("EXHAUSTIVE_DEPTH")
+        .map(|depth| depth.parse().unwrap())
+        .unwrap_or(7);
+    exhaustive::<2>(depth + 2);
+    exhaustive::<3>(depth + 1);
+    exhaustive::<4>(depth);
+    exhaustive::<5>(depth);
+}
@@ -1,7 +1,17 @@
+#[cfg(test)]
+mod differential;
 mod dll;
+#[cfg(test)]
+mod exhaustive;
+#[cfg(test)]
+mod model;
+mod queue;
 mod ringbuf;
+mod tombstone;
 
 pub use dll::*;
+pub use queue::*;
 pub use ringbuf::*;
+pub use tombstone::*;
 
 pub const MAX_NUM_EVENTS: usize = 488;
@@ -0,0 +1,263 @@
+//! Reference model of the queues, checked against both backends on random operation
+//! sequences.
+
+use super::{
+    Capacity, DLLEventQueue, EventQueue, Queue, SlotIndex, TombstoneCapacity, TombstoneEventQueue,
+};
+use crate::error::PushError;
+use bytemuck::Zeroable;
+use proptest::prelude::*;
+use std::collections::VecDeque;
+
+/// Operation applied to a queue and to the model alike. Operations a backend does not have
+/// (`DeleteSlot` on the ring buffers, `RevertPushes` on the list and the tombstone ring
+/// buffer) are skipped for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Push,
+    Pop,
+    PopBack,
+    /// Removes the event at this index in FIFO order.
+    RemoveAt(usize),
+    /// Deletes the event stored in this slot, which may be free or out of range.
+    DeleteSlot(usize),
+    /// Truncates the queue to this length.
+    RevertPushes(usize),
+    /// Visits this many events from the front, from the back and from both ends.
+    Iterate(usize),
+}
+
+/// Queue semantics the backends are checked against. Items are the sequence number they
+/// were pushed with.
+#[derive(Debug, Clone, Default)]
+pub struct Model {
+    pub capacity: usize,
+    pub items: VecDeque<u64>,
+    pub seq_num: u64,
+}
+
+impl Model {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ..Self::default()
+        }
+    }
+
+    /// Pushes the next item, returning it unless the queue is full.
+    pub fn push(&mut self) -> Option<u64> {
+        if self.items.len() == self.capacity {
+            return None;
+        }
+        let item = self.seq_num;
+        self.items.push_back(item);
+        self.seq_num += 1;
+        Some(item)
+    }
+
+    pub fn pop(&mut self) -> Option<u64> {
+        self.items.pop_front()
+    }
+
+    /// Removes the last item, leaving the sequence number unchanged.
+    pub fn pop_back(&mut self) -> Option<u64> {
+        self.items.pop_back()
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<u64> {
+        self.items.remove(index)
+    }
+
+    /// Drops the last pushes down to `len` items, also reverting their sequence numbers.
+    pub fn revert_pushes(&mut self, len: usize) -> bool {
+        if len > self.items.len() {
+            return false;
+        }
+        self.seq_num -= (self.items.len() - len) as u64;
+        self.items.truncate(len);
+        true
+    }
+}
+
+/// Checks what every queue exposes through the [`Queue`] trait against the model.
+pub fn assert_matches(queue: &impl Queue<Item = u64>, model: &Model) {
+    assert_eq!(queue.len(), model.items.len());
+    assert_eq!(queue.seq_num(), model.seq_num);
+    assert_eq!(queue.peek_front(), model.items.front());
+    assert_eq!(queue.peek_back(), model.items.back());
+    assert_eq!(queue.iter().len(), model.items.len());
+    assert!(queue.iter().eq(model.items.iter()));
+    assert!(queue.iter().rev().eq(model.items.iter().rev()));
+}
+
+/// Applies `op` to a ring buffer and to the model, checking that both agree on the result.
+pub fn apply_ring<const N: usize>(queue: &mut EventQueue<N, u64>, model: &mut Model, op: Op) {
+    match op {
+        Op::Push => push(queue, model),
+        Op::Pop => assert_eq!(queue.pop_front().ok(), model.pop()),
+        Op::PopBack => assert_eq!(queue.pop_back().ok(), model.pop_back()),
+        Op::RemoveAt(index) => assert_eq!(queue.remove_at(index).ok(), model.remove(index)),
+        Op::DeleteSlot(_) => {}
+        Op::RevertPushes(len) => {
+            assert_eq!(queue.revert_pushes(len).is_ok(), model.revert_pushes(len));
+        }
+        Op::Iterate(n) => iterate(queue, model, n),
+    }
+    assert_matches(queue, model);
+}
+
+/// Applies `op` to a list and to the model, checking that both agree on the result.
+pub fn apply_list<const N: usize, I: SlotIndex>(
+    queue: &mut DLLEventQueue<N, I, u64>,
+    model: &mut Model,
+    op: Op,
+) {
+    match op {
+        Op::Push => push(queue, model),
+        Op::Pop => assert_eq!(queue.delete().ok(), model.pop()),
+        Op::PopBack => assert_eq!(queue.pop_back().ok(), model.pop_back()),
+        Op::RemoveAt(index) => {
+            assert_eq!(Queue::remove_at(queue, index).ok(), model.remove(index));
+        }
+        Op::DeleteSlot(slot) => {
+            let index = queue.iter().position(|e| e.slot == slot);
+            let expected = index.and_then(|index| model.remove(index));
+            assert_eq!(queue.delete_slot(slot).ok(), expected);
+        }
+        Op::RevertPushes(_) => {}
+        Op::Iterate(n) => iterate(queue, model, n),
+    }
+    assert_matches(queue, model);
+    queue.validate().unwrap();
+}
+
+/// Applies `op` to a tombstone ring buffer and to the model, checking that both agree on the
+/// result and that no tombstone is left at either end.
+pub fn apply_tombstone<const N: usize>(
+    queue: &mut TombstoneEventQueue<N, u64>,
+    model: &mut Model,
+    op: Op,
+) where
+    Capacity<N>: TombstoneCapacity,
+{
+    match op {
+        Op::Push => push(queue, model),
+        Op::Pop => assert_eq!(queue.pop_front().ok(), model.pop()),
+        Op::PopBack => assert_eq!(queue.pop_back().ok(), model.pop_back()),
+        Op::RemoveAt(index) => assert_eq!(queue.remove_at(index).ok(), model.remove(index)),
+        Op::DeleteSlot(_) | Op::RevertPushes(_) => {}
+        Op::Iterate(n) => iterate(queue, model, n),
+    }
+    assert_matches(queue, model);
+    assert!(queue.span() <= N);
+    assert!(queue.tombstone_count() <= queue.len());
+}
+
+fn push(queue: &mut impl Queue<Item = u64>, model: &mut Model) {
+    let item = queue.seq_num();
+    match model.push() {
+        Some(expected) => {
+            assert_eq!(item, expected);
+            queue.push_back(item).unwrap();
+        }
+        None => assert_eq!(queue.push_back(item), Err(PushError::Full(item))),
+    }
+}
+
+fn iterate(queue: &impl Queue<Item = u64>, model: &Model, n: usize) {
+    assert!(queue.iter().take(n).eq(model.items.iter().take(n)));
+    assert!(queue
+        .iter()
+        .rev()
+        .take(n)
+        .eq(model.items.iter().rev().take(n)));
+
+    // meeting in the middle from both ends
+    let mut iter = queue.iter();
+    let mut expected = model.items.iter();
+    for i in 0..n {
+        if i % 2 == 0 {
+            assert_eq!(iter.next(), expected.next());
+        } else {
+            assert_eq!(iter.next_back(), expected.next_back());
+        }
+        assert_eq!(iter.len(), expected.len());
+    }
+}
+
+/// Operations on a queue of `capacity` events, with indices, slots and lengths reaching a
+/// little past it so that failures are exercised too.
+pub fn op(capacity: usize) -> impl Strategy<Value = Op> {
+    let past = capacity + 2;
+    prop_oneof![
+        4 => Just(Op::Push),
+        2 => Just(Op::Pop),
+        1 => Just(Op::PopBack),
+        1 => (0..past).prop_map(Op::RemoveAt),
+        1 => (0..past).prop_map(Op::DeleteSlot),
+        1 => (0..past).prop_map(Op::RevertPushes),
+        1 => (0..past).prop_map(Op::Iterate),
+    ]
+}
+
+fn check_ring<const N: usize>(ops: &[Op]) {
+    let mut queue = EventQueue::<N, u64>::zeroed();
+    queue.init();
+    let mut model = Model::new(N);
+    for &op in ops {
+        apply_ring(&mut queue, &mut model, op);
+    }
+}
+
+fn check_tombstone<const N: usize>(ops: &[Op])
+where
+    Capacity<N>: TombstoneCapacity,
+{
+    let mut queue = TombstoneEventQueue::<N, u64>::zeroed();
+    queue.init();
+    let mut model = Model::new(N);
+    for &op in ops {
+        apply_tombstone(&mut queue, &mut model, op);
+    }
+}
+
+fn check_list<const N: usize, I: SlotIndex>(ops: &[Op]) {
+    let mut queue = DLLEventQueue::<N, I, u64>::zeroed();
+    queue.init();
+    let mut model = Model::new(N);
+    for &op in ops {
+        apply_list(&mut queue, &mut model, op);
+    }
+}
+
+proptest! {
+    #[test]
+    fn ring_buffer_matches_model(ops in prop::collection::vec(op(8), 0..200)) {
+        check_ring::<8>(&ops);
+    }
+
+    #[test]
+    fn large_ring_buffer_matches_model(ops in prop::collection::vec(op(64), 0..400)) {
+        check_ring::<64>(&ops);
+    }
+
+    #[test]
+    fn tombstone_ring_buffer_matches_model(ops in prop::collection::vec(op(8), 0..200)) {
+        check_tombstone::<8>(&ops);
+    }
+
+    #[test]
+    fn large_tombstone_ring_buffer_matches_model(ops in prop::collection::vec(op(64), 0..400)) {
+        check_tombstone::<64>(&ops);
+    }
+
+    #[test]
+    fn list_matches_model(ops in prop::collection::vec(op(8), 0..200)) {
+        check_list::<8, u16>(&ops);
+    }
+
+    #[test]
+    fn large_list_matches_model(ops in prop::collection::vec(op(64), 0..400)) {
+        check_list::<64, u32>(&ops);
+    }
+}
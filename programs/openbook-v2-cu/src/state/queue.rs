@@ -0,0 +1,176 @@
+use crate::error::PushError;
+use anchor_lang::prelude::*;
+use bytemuck::Pod;
+use openbook_v2::state::AnyEvent;
+use static_assertions::const_assert;
+use std::mem::{align_of, size_of};
+
+/// FIFO event queue API shared by every queue backend, so benchmarks and callers can be
+/// written once against any implementation.
+pub trait Queue {
+    /// Element stored in the queue, `AnyEvent` for an event queue.
+    type Item: bytemuck::Pod;
+
+    type Iter<'a>: DoubleEndedIterator<Item = &'a Self::Item> + ExactSizeIterator
+    where
+        Self: 'a;
+
+    /// Resets the queue to empty, ready for its first push.
+    fn init(&mut self);
+
+    fn len(&self) -> usize;
+
+    fn capacity(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn is_full(&self) -> bool {
+        self.len() == self.capacity()
+    }
+
+    /// Sequence number the next pushed event gets. Only pushes advance it: removing events
+    /// from either end leaves it unchanged, `EventQueue::revert_pushes` being the one way to
+    /// rewind it.
+    fn seq_num(&self) -> u64;
+
+    /// Appends an event, handing it back if the queue is full or corrupted.
+    fn push_back(&mut self, value: Self::Item) -> std::result::Result<(), PushError<Self::Item>>;
+
+    fn pop_front(&mut self) -> Result<Self::Item>;
+
+    fn peek_front(&self) -> Option<&Self::Item>;
+
+    /// Removes the most recently pushed event still in the queue.
+    fn pop_back(&mut self) -> Result<Self::Item>;
+
+    fn peek_back(&self) -> Option<&Self::Item>;
+
+    /// Removes the event at `index` in FIFO order, 0 being the front, keeping the order of
+    /// the remaining events.
+    fn remove_at(&mut self, index: usize) -> Result<Self::Item>;
+
+    /// Iterates the events from front to back, or from back to front once reversed.
+    fn iter(&self) -> Self::Iter<'_>;
+}
+
+/// Item a queue can hold: a `Pod` type whose size is a multiple of 8 bytes and whose alignment
+/// is at most 8, so that it fills the slots of a ring buffer and follows the 8 bytes of links
+/// of a list node without padding.
+///
+/// Sealed, as the `Pod` impls of the queues rely on it.
+pub trait QueueItem: sealed::Sealed + Pod {}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! queue_items {
+    ($($item:ty),*) => {
+        $(
+            impl sealed::Sealed for $item {}
+            impl QueueItem for $item {}
+            const_assert!(size_of::<$item>() % 8 == 0 && align_of::<$item>() <= 8);
+        )*
+    };
+}
+
+queue_items!(u64, AnyEvent, [u8; 32], [u8; 88], [u8; 512]);
+
+/// Queue stored in a zero-copy account.
+///
+/// `Pod` is implemented for the queues without the padding checks of the derive, which does
+/// not support generic structs, but only over [`QueueItem`]s and, for a tombstone ring
+/// buffer, [`super::TombstoneCapacity`]s. [`Self::SIZE`] adds up the sizes
+/// of the fields, and [`Self::check_layout`], called by every [`Queue::init`], fails the build
+/// of any queue whose struct is larger all the same, i.e. holds padding.
+pub trait QueueAccount: Sized {
+    /// Size of the account data, without the discriminator: the sum of the sizes of the fields.
+    const SIZE: usize;
+    const CAPACITY: usize;
+    /// Largest capacity the header and links can address.
+    const MAX_CAPACITY: usize;
+
+    const LAYOUT: () = {
+        assert!(Self::CAPACITY > 0 && Self::CAPACITY <= Self::MAX_CAPACITY);
+        assert!(size_of::<Self>() == Self::SIZE);
+        assert!(size_of::<Self>() % 8 == 0);
+    };
+
+    fn check_layout() {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::LAYOUT;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{DLLEventQueue, EventQueue, TombstoneEventQueue, MAX_NUM_EVENTS};
+    use bytemuck::Zeroable;
+
+    fn event(event_type: u8) -> AnyEvent {
+        let mut event = AnyEvent::zeroed();
+        event.event_type = event_type;
+        event
+    }
+
+    fn fill_and_drain(queue: &mut impl Queue<Item = AnyEvent>) {
+        queue.init();
+        assert!(queue.is_empty());
+        assert_eq!(queue.capacity(), MAX_NUM_EVENTS);
+        assert!(queue.pop_front().is_err());
+
+        for i in 0..MAX_NUM_EVENTS {
+            queue.push_back(event(i as u8)).unwrap();
+        }
+        assert!(queue.is_full());
+        assert_eq!(queue.seq_num(), MAX_NUM_EVENTS as u64);
+        assert!(matches!(
+            queue.push_back(event(1)),
+            Err(PushError::Full(rejected)) if rejected.event_type == 1
+        ));
+
+        assert_eq!(queue.peek_front().unwrap().event_type, 0);
+        let last = (MAX_NUM_EVENTS - 1) as u8;
+        assert_eq!(queue.peek_back().unwrap().event_type, last);
+        assert_eq!(queue.pop_back().unwrap().event_type, last);
+        assert_eq!(queue.seq_num(), MAX_NUM_EVENTS as u64);
+        queue.push_back(event(0)).unwrap();
+        assert_eq!(queue.seq_num(), MAX_NUM_EVENTS as u64 + 1);
+        assert_eq!(queue.peek_back().unwrap().event_type, 0);
+        assert_eq!(queue.remove_at(3).unwrap().event_type, 3);
+        assert!(queue.remove_at(queue.len()).is_err());
+        assert_eq!(queue.iter().len(), MAX_NUM_EVENTS - 1);
+        assert_eq!(queue.iter().count(), MAX_NUM_EVENTS - 1);
+        assert_eq!(queue.iter().next_back().unwrap().event_type, 0);
+        assert_eq!(queue.iter().filter(|e| e.event_type == 3).count(), 1);
+
+        while !queue.is_empty() {
+            queue.pop_front().unwrap();
+        }
+        assert!(queue.peek_front().is_none());
+        assert!(queue.peek_back().is_none());
+        assert!(queue.pop_back().is_err());
+        assert_eq!(queue.iter().count(), 0);
+    }
+
+    #[test]
+    fn ring_buffer() {
+        let mut eq = <EventQueue>::zeroed();
+        fill_and_drain(&mut eq);
+    }
+
+    #[test]
+    fn doubly_linked_list() {
+        let mut eq = <DLLEventQueue>::zeroed();
+        fill_and_drain(&mut eq);
+    }
+
+    #[test]
+    fn tombstones() {
+        let mut eq = <TombstoneEventQueue>::zeroed();
+        fill_and_drain(&mut eq);
+    }
+}
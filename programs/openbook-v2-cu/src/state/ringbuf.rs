@@ -1,31 +1,41 @@
-use super::MAX_NUM_EVENTS;
+use super::{Queue, QueueAccount, QueueItem, MAX_NUM_EVENTS};
+use crate::error::{PushError, QueueError};
 use anchor_lang::prelude::*;
-use openbook_v2::{error::OpenBookError, state::AnyEvent};
+use bytemuck::Zeroable;
+use openbook_v2::state::AnyEvent;
 use static_assertions::const_assert_eq;
 
 pub trait QueueHeader: bytemuck::Pod {
-    type Item: bytemuck::Pod + Copy;
-
     fn head(&self) -> usize;
     fn set_head(&mut self, value: u32);
     fn count(&self) -> usize;
     fn set_count(&mut self, value: u32);
 
     fn incr_event_id(&mut self);
-    fn decr_event_id(&mut self, n: u64);
+    /// Fails rather than wrap below zero on a corrupted header.
+    fn decr_event_id(&mut self, n: u64) -> Result<()>;
 }
 
-#[account(zero_copy)]
-pub struct EventQueue {
+#[account(zero_copy(unsafe))]
+#[repr(C)]
+pub struct EventQueue<const N: usize = MAX_NUM_EVENTS, T: QueueItem = AnyEvent> {
     pub header: EventQueueHeader,
-    pub buf: [AnyEvent; MAX_NUM_EVENTS as usize],
+    pub buf: [T; N],
     pub reserved: [u8; 64],
 }
-const_assert_eq!(std::mem::size_of::<EventQueue>(), 16 + 488 * 200 + 64);
-const_assert_eq!(std::mem::size_of::<EventQueue>(), 97680);
-const_assert_eq!(std::mem::size_of::<EventQueue>() % 8, 0);
+const_assert_eq!(std::mem::size_of::<EventQueue>(), <EventQueue>::SIZE);
+const_assert_eq!(
+    std::mem::size_of::<EventQueue<64, [u8; 32]>>(),
+    EventQueue::<64, [u8; 32]>::SIZE
+);
+
+impl<const N: usize, T: QueueItem> QueueAccount for EventQueue<N, T> {
+    const SIZE: usize = std::mem::size_of::<EventQueueHeader>() + N * std::mem::size_of::<T>() + 64;
+    const CAPACITY: usize = N;
+    const MAX_CAPACITY: usize = u32::MAX as usize;
+}
 
-impl EventQueue {
+impl<const N: usize, T: QueueItem> EventQueue<N, T> {
     pub fn len(&self) -> usize {
         self.header.count()
     }
@@ -38,9 +48,12 @@ impl EventQueue {
         self.header.count() == self.buf.len()
     }
 
-    pub fn push_back(&mut self, value: AnyEvent) -> std::result::Result<(), AnyEvent> {
+    pub fn push_back(&mut self, value: T) -> std::result::Result<(), PushError<T>> {
         if self.full() {
-            return Err(value);
+            return Err(PushError::Full(value));
+        }
+        if self.header.count() > N || self.header.seq_num == u64::MAX {
+            return Err(PushError::Corrupted(value));
         }
         let slot = (self.header.head() + self.header.count()) % self.buf.len();
         self.buf[slot] = value;
@@ -52,24 +65,27 @@ impl EventQueue {
         Ok(())
     }
 
-    pub fn peek_front(&self) -> Option<&AnyEvent> {
+    pub fn peek_front(&self) -> Option<&T> {
         if self.is_empty() {
             return None;
         }
-        Some(&self.buf[self.header.head()])
+        self.buf.get(self.header.head())
     }
 
-    pub fn peek_front_mut(&mut self) -> Option<&mut AnyEvent> {
+    pub fn peek_front_mut(&mut self) -> Option<&mut T> {
         if self.is_empty() {
             return None;
         }
-        Some(&mut self.buf[self.header.head()])
+        self.buf.get_mut(self.header.head())
     }
 
-    pub fn pop_front(&mut self) -> Result<AnyEvent> {
-        require!(!self.is_empty(), OpenBookError::SomeError);
+    pub fn pop_front(&mut self) -> Result<T> {
+        require!(!self.is_empty(), QueueError::QueueEmpty);
 
-        let value = self.buf[self.header.head()];
+        let value = *self
+            .buf
+            .get(self.header.head())
+            .ok_or(QueueError::CorruptedQueue)?;
 
         let count = self.header.count();
         self.header.set_count((count - 1) as u32);
@@ -80,41 +96,174 @@ impl EventQueue {
         Ok(value)
     }
 
+    pub fn peek_back(&self) -> Option<&T> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(&self.buf[self.back_slot()])
+    }
+
+    /// Removes the last event, leaving `seq_num` unchanged unlike [`Self::revert_pushes`].
+    pub fn pop_back(&mut self) -> Result<T> {
+        require!(!self.is_empty(), QueueError::QueueEmpty);
+
+        let value = self.buf[self.back_slot()];
+
+        let count = self.header.count();
+        self.header.set_count((count - 1) as u32);
+
+        Ok(value)
+    }
+
+    /// Slot of the last event of a non-empty queue.
+    fn back_slot(&self) -> usize {
+        (self.header.head() + self.header.count() - 1) % self.buf.len()
+    }
+
+    /// Removes the event at `index` in FIFO order, shifting the events on its shorter side by
+    /// one slot to close the gap, so at most half of the queue moves.
+    pub fn remove_at(&mut self, index: usize) -> Result<T> {
+        let len = self.len();
+        require!(index < len, QueueError::IndexOutOfRange);
+        let head = self.header.head();
+        require!(head < N && len <= N, QueueError::CorruptedQueue);
+
+        let slot = |i: usize| (head + i) % N;
+        let value = self.buf[slot(index)];
+
+        if index < len - 1 - index {
+            // move the events before it one slot towards the back
+            for i in (0..index).rev() {
+                self.buf[slot(i + 1)] = self.buf[slot(i)];
+            }
+            self.header.set_head(slot(1) as u32);
+        } else {
+            // move the events after it one slot towards the front
+            for i in index..len - 1 {
+                self.buf[slot(i)] = self.buf[slot(i + 1)];
+            }
+        }
+        self.header.set_count((len - 1) as u32);
+
+        Ok(value)
+    }
+
     pub fn revert_pushes(&mut self, desired_len: usize) -> Result<()> {
-        require!(desired_len <= self.header.count(), OpenBookError::SomeError);
+        require!(
+            desired_len <= self.header.count(),
+            QueueError::RevertLengthTooLarge
+        );
         let len_diff = self.header.count() - desired_len;
+        self.header.decr_event_id(len_diff as u64)?;
         self.header.set_count(desired_len as u32);
-        self.header.decr_event_id(len_diff as u64);
         Ok(())
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = &AnyEvent> {
+    pub fn iter(&self) -> EventQueueIterator<'_, N, T> {
         EventQueueIterator {
             queue: self,
             index: 0,
+            // a corrupted count would send the iteration around the buffer again and again
+            back: self.len().min(N),
         }
     }
 }
 
-struct EventQueueIterator<'a> {
-    queue: &'a EventQueue,
+impl<const N: usize, T: QueueItem> Queue for EventQueue<N, T> {
+    type Item = T;
+    type Iter<'a> = EventQueueIterator<'a, N, T>;
+
+    fn init(&mut self) {
+        Self::check_layout();
+        self.header = EventQueueHeader::zeroed();
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn seq_num(&self) -> u64 {
+        self.header.seq_num
+    }
+
+    fn push_back(&mut self, value: T) -> std::result::Result<(), PushError<T>> {
+        self.push_back(value)
+    }
+
+    fn pop_front(&mut self) -> Result<T> {
+        self.pop_front()
+    }
+
+    fn peek_front(&self) -> Option<&T> {
+        self.peek_front()
+    }
+
+    fn pop_back(&mut self) -> Result<T> {
+        self.pop_back()
+    }
+
+    fn peek_back(&self) -> Option<&T> {
+        self.peek_back()
+    }
+
+    fn remove_at(&mut self, index: usize) -> Result<T> {
+        self.remove_at(index)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+}
+
+pub struct EventQueueIterator<'a, const N: usize, T: QueueItem> {
+    queue: &'a EventQueue<N, T>,
+    /// Index of the next event from the front.
     index: usize,
+    /// Index past the next event from the back.
+    back: usize,
+}
+
+impl<'a, const N: usize, T: QueueItem> EventQueueIterator<'a, N, T> {
+    fn at(&self, index: usize) -> &'a T {
+        &self.queue.buf[(self.queue.header.head() + index) % self.queue.buf.len()]
+    }
 }
 
-impl<'a> Iterator for EventQueueIterator<'a> {
-    type Item = &'a AnyEvent;
+impl<'a, const N: usize, T: QueueItem> Iterator for EventQueueIterator<'a, N, T> {
+    type Item = &'a T;
     fn next(&mut self) -> Option<Self::Item> {
-        if self.index == self.queue.len() {
+        if self.index == self.back {
             None
         } else {
-            let item =
-                &self.queue.buf[(self.queue.header.head() + self.index) % self.queue.buf.len()];
+            let item = self.at(self.index);
             self.index += 1;
             Some(item)
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.index;
+        (len, Some(len))
+    }
+}
+
+impl<'a, const N: usize, T: QueueItem> DoubleEndedIterator for EventQueueIterator<'a, N, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.index == self.back {
+            None
+        } else {
+            self.back -= 1;
+            Some(self.at(self.back))
+        }
+    }
 }
 
+impl<'a, const N: usize, T: QueueItem> ExactSizeIterator for EventQueueIterator<'a, N, T> {}
+
 #[zero_copy]
 pub struct EventQueueHeader {
     head: u32,
@@ -125,8 +274,6 @@ const_assert_eq!(std::mem::size_of::<EventQueueHeader>(), 16);
 const_assert_eq!(std::mem::size_of::<EventQueueHeader>() % 8, 0);
 
 impl QueueHeader for EventQueueHeader {
-    type Item = AnyEvent;
-
     fn head(&self) -> usize {
         self.head as usize
     }
@@ -142,7 +289,11 @@ impl QueueHeader for EventQueueHeader {
     fn incr_event_id(&mut self) {
         self.seq_num += 1;
     }
-    fn decr_event_id(&mut self, n: u64) {
-        self.seq_num -= n;
+    fn decr_event_id(&mut self, n: u64) -> Result<()> {
+        self.seq_num = self
+            .seq_num
+            .checked_sub(n)
+            .ok_or(QueueError::CorruptedQueue)?;
+        Ok(())
     }
 }
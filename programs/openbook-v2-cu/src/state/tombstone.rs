@@ -0,0 +1,542 @@
+use super::{Queue, QueueAccount, QueueHeader, QueueItem, MAX_NUM_EVENTS};
+use crate::error::{PushError, QueueError};
+use anchor_lang::prelude::*;
+use bytemuck::Zeroable;
+use openbook_v2::state::AnyEvent;
+use static_assertions::const_assert_eq;
+use std::mem::size_of;
+
+/// Ring buffer removing events from its middle by marking them as tombstones instead of
+/// shifting the events around them, as an alternative to both [`super::EventQueue`] and
+/// [`super::DLLEventQueue`] for consuming the events of one owner.
+///
+/// Tombstones left at either end are dropped right away, so the front and back events are
+/// always live. The ones in between are skipped by iteration and reclaimed by
+/// [`Self::compact`] once they outnumber the events or a push finds no slot past the back.
+#[account(zero_copy(unsafe))]
+#[repr(C)]
+pub struct TombstoneEventQueue<const N: usize = MAX_NUM_EVENTS, T: QueueItem = AnyEvent>
+where
+    Capacity<N>: TombstoneCapacity,
+{
+    pub header: TombstoneHeader,
+    pub buf: [T; N],
+    /// Non-zero for the slots whose event was removed. A flag per slot rather than a reserved
+    /// `event_type`, so that any item can be queued.
+    pub tombstones: [u8; N],
+    pub reserved: [u8; 64],
+}
+const_assert_eq!(
+    size_of::<TombstoneEventQueue>(),
+    <TombstoneEventQueue>::SIZE
+);
+const_assert_eq!(
+    size_of::<TombstoneEventQueue<64, [u8; 32]>>(),
+    TombstoneEventQueue::<64, [u8; 32]>::SIZE
+);
+
+/// Marks the capacities of a [`TombstoneEventQueue`], multiples of 8 so that its one-byte
+/// tombstones end on the alignment of the queue without padding.
+pub struct Capacity<const N: usize>;
+
+/// Sealed, as the `Pod` impl of [`TombstoneEventQueue`] relies on it.
+pub trait TombstoneCapacity: sealed::Sealed {}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! tombstone_capacities {
+    ($($n:expr),*) => {
+        $(
+            impl sealed::Sealed for Capacity<{ $n }> {}
+            impl TombstoneCapacity for Capacity<{ $n }> {}
+            const_assert_eq!($n % 8, 0);
+        )*
+    };
+}
+
+tombstone_capacities!(
+    8,
+    16,
+    32,
+    64,
+    128,
+    256,
+    MAX_NUM_EVENTS,
+    512,
+    1024,
+    2048,
+    4096
+);
+
+impl<const N: usize, T: QueueItem> QueueAccount for TombstoneEventQueue<N, T>
+where
+    Capacity<N>: TombstoneCapacity,
+{
+    const SIZE: usize = size_of::<TombstoneHeader>() + N * (size_of::<T>() + 1) + 64;
+    const CAPACITY: usize = N;
+    const MAX_CAPACITY: usize = u32::MAX as usize;
+}
+
+impl<const N: usize, T: QueueItem> TombstoneEventQueue<N, T>
+where
+    Capacity<N>: TombstoneCapacity,
+{
+    pub fn len(&self) -> usize {
+        self.header.count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn full(&self) -> bool {
+        self.len() == N
+    }
+
+    /// Slots from the front event to the back one, tombstones included.
+    pub fn span(&self) -> usize {
+        self.header.span()
+    }
+
+    pub fn tombstone_count(&self) -> usize {
+        self.span().saturating_sub(self.len())
+    }
+
+    /// Slot `i` positions past the front event.
+    fn slot(&self, i: usize) -> usize {
+        (self.header.head() + i) % N
+    }
+
+    fn is_tombstone(&self, slot: usize) -> bool {
+        self.tombstones[slot] != 0
+    }
+
+    /// Appends an event, compacting the queue first if the slot past the back is the front
+    /// one. Hands the event back if the queue is full.
+    pub fn push_back(&mut self, value: T) -> std::result::Result<(), PushError<T>> {
+        if self.full() {
+            return Err(PushError::Full(value));
+        }
+        if self.check().is_err() || self.header.seq_num == u64::MAX {
+            return Err(PushError::Corrupted(value));
+        }
+        if self.span() >= N {
+            self.compact();
+        }
+
+        let slot = self.slot(self.span());
+        self.buf[slot] = value;
+        self.tombstones[slot] = 0;
+
+        self.header.set_span(self.span() as u32 + 1);
+        self.header.set_count(self.len() as u32 + 1);
+        self.header.incr_event_id();
+        Ok(())
+    }
+
+    pub fn peek_front(&self) -> Option<&T> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(&self.buf[self.slot(0)])
+    }
+
+    pub fn pop_front(&mut self) -> Result<T> {
+        require!(!self.is_empty(), QueueError::QueueEmpty);
+        self.check()?;
+
+        let value = self.buf[self.slot(0)];
+        self.header.set_head(self.slot(1) as u32);
+        self.header.set_span(self.span() as u32 - 1);
+        self.header.set_count(self.len() as u32 - 1);
+        self.reclaim();
+
+        Ok(value)
+    }
+
+    pub fn peek_back(&self) -> Option<&T> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(&self.buf[self.slot(self.span().checked_sub(1)?)])
+    }
+
+    /// Removes the last event, leaving `seq_num` unchanged.
+    pub fn pop_back(&mut self) -> Result<T> {
+        require!(!self.is_empty(), QueueError::QueueEmpty);
+        self.check()?;
+
+        let value = self.buf[self.slot(self.span() - 1)];
+        self.header.set_span(self.span() as u32 - 1);
+        self.header.set_count(self.len() as u32 - 1);
+        self.reclaim();
+
+        Ok(value)
+    }
+
+    /// Removes the event at `index` in FIFO order by marking its slot as a tombstone. The
+    /// slot is found by counting the events from the closer end.
+    pub fn remove_at(&mut self, index: usize) -> Result<T> {
+        let len = self.len();
+        require!(index < len, QueueError::IndexOutOfRange);
+        self.check()?;
+
+        let slot = self.live_slot(index).ok_or(QueueError::CorruptedQueue)?;
+        let value = self.buf[slot];
+        self.tombstones[slot] = 1;
+        self.header.set_count((len - 1) as u32);
+        self.reclaim();
+
+        Ok(value)
+    }
+
+    /// Slot of the event at `index` in FIFO order, if the span holds that many events.
+    fn live_slot(&self, index: usize) -> Option<usize> {
+        let len = self.len();
+        let span = self.span();
+        if index < len - 1 - index {
+            (0..span)
+                .map(|i| self.slot(i))
+                .filter(|&slot| !self.is_tombstone(slot))
+                .nth(index)
+        } else {
+            (0..span)
+                .rev()
+                .map(|i| self.slot(i))
+                .filter(|&slot| !self.is_tombstone(slot))
+                .nth(len - 1 - index)
+        }
+    }
+
+    /// Drops the tombstones left at either end by a removal, then compacts the queue if the
+    /// ones in between outnumber the events.
+    fn reclaim(&mut self) {
+        self.skip_front();
+        self.skip_back();
+        if self.tombstone_count() > self.len() {
+            self.compact();
+        }
+    }
+
+    /// Drops the tombstones at the front, up to the first event.
+    fn skip_front(&mut self) {
+        while self.span() > self.len() && self.is_tombstone(self.slot(0)) {
+            let head = self.slot(0);
+            self.tombstones[head] = 0;
+            self.header.set_head(self.slot(1) as u32);
+            self.header.set_span(self.span() as u32 - 1);
+        }
+    }
+
+    /// Drops the tombstones at the back, down to the last event.
+    fn skip_back(&mut self) {
+        while self.span() > self.len() && self.is_tombstone(self.slot(self.span() - 1)) {
+            let back = self.slot(self.span() - 1);
+            self.tombstones[back] = 0;
+            self.header.set_span(self.span() as u32 - 1);
+        }
+    }
+
+    /// Moves the events towards the front over the tombstones between them, keeping their
+    /// order and freeing the slots past the back.
+    pub fn compact(&mut self) {
+        let mut len = 0;
+        for i in 0..self.span().min(N) {
+            let from = self.slot(i);
+            if self.is_tombstone(from) {
+                self.tombstones[from] = 0;
+            } else {
+                if len != i {
+                    let to = self.slot(len);
+                    self.buf[to] = self.buf[from];
+                }
+                len += 1;
+            }
+        }
+        self.header.set_span(len as u32);
+    }
+
+    /// Fails on a header no sequence of operations can produce, before it is trusted to walk
+    /// the buffer.
+    fn check(&self) -> Result<()> {
+        require!(
+            self.header.head() < N && self.len() <= self.span() && self.span() <= N,
+            QueueError::CorruptedQueue
+        );
+        Ok(())
+    }
+
+    pub fn iter(&self) -> TombstoneEventQueueIterator<'_, N, T> {
+        TombstoneEventQueueIterator {
+            queue: self,
+            index: 0,
+            back: self.span().min(N),
+            // a corrupted count cannot ask for more events than there are slots
+            remaining: self.len().min(self.span()).min(N),
+        }
+    }
+}
+
+impl<const N: usize, T: QueueItem> Queue for TombstoneEventQueue<N, T>
+where
+    Capacity<N>: TombstoneCapacity,
+{
+    type Item = T;
+    type Iter<'a> = TombstoneEventQueueIterator<'a, N, T>;
+
+    /// Resets the header only: the tombstone of a slot is cleared whenever it is pushed to.
+    fn init(&mut self) {
+        Self::check_layout();
+        self.header = TombstoneHeader::zeroed();
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn capacity(&self) -> usize {
+        N
+    }
+
+    fn seq_num(&self) -> u64 {
+        self.header.seq_num
+    }
+
+    fn push_back(&mut self, value: T) -> std::result::Result<(), PushError<T>> {
+        self.push_back(value)
+    }
+
+    fn pop_front(&mut self) -> Result<T> {
+        self.pop_front()
+    }
+
+    fn peek_front(&self) -> Option<&T> {
+        self.peek_front()
+    }
+
+    fn pop_back(&mut self) -> Result<T> {
+        self.pop_back()
+    }
+
+    fn peek_back(&self) -> Option<&T> {
+        self.peek_back()
+    }
+
+    fn remove_at(&mut self, index: usize) -> Result<T> {
+        self.remove_at(index)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+}
+
+/// Skips the tombstones between the events, but only while more slots than events are left,
+/// so it yields exactly `remaining` events even if the flags disagree with the header.
+pub struct TombstoneEventQueueIterator<'a, const N: usize, T: QueueItem>
+where
+    Capacity<N>: TombstoneCapacity,
+{
+    queue: &'a TombstoneEventQueue<N, T>,
+    /// Position past the front of the next slot from the front.
+    index: usize,
+    /// Position past the front of the slot after the next one from the back.
+    back: usize,
+    /// Events left between `index` and `back`.
+    remaining: usize,
+}
+
+impl<'a, const N: usize, T: QueueItem> Iterator for TombstoneEventQueueIterator<'a, N, T>
+where
+    Capacity<N>: TombstoneCapacity,
+{
+    type Item = &'a T;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.remaining > 0 {
+            let slot = self.queue.slot(self.index);
+            self.index += 1;
+            if self.queue.is_tombstone(slot) && self.back - self.index >= self.remaining {
+                continue;
+            }
+            self.remaining -= 1;
+            return Some(&self.queue.buf[slot]);
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, const N: usize, T: QueueItem> DoubleEndedIterator for TombstoneEventQueueIterator<'a, N, T>
+where
+    Capacity<N>: TombstoneCapacity,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        while self.remaining > 0 {
+            self.back -= 1;
+            let slot = self.queue.slot(self.back);
+            if self.queue.is_tombstone(slot) && self.back - self.index >= self.remaining {
+                continue;
+            }
+            self.remaining -= 1;
+            return Some(&self.queue.buf[slot]);
+        }
+        None
+    }
+}
+
+impl<'a, const N: usize, T: QueueItem> ExactSizeIterator for TombstoneEventQueueIterator<'a, N, T> where
+    Capacity<N>: TombstoneCapacity
+{
+}
+
+#[zero_copy]
+pub struct TombstoneHeader {
+    head: u32,
+    /// Slots from `head` to the back event, tombstones included.
+    span: u32,
+    count: u32,
+    _padd: u32,
+    pub seq_num: u64,
+}
+const_assert_eq!(size_of::<TombstoneHeader>(), 24);
+const_assert_eq!(size_of::<TombstoneHeader>() % 8, 0);
+
+impl TombstoneHeader {
+    pub fn span(&self) -> usize {
+        self.span as usize
+    }
+
+    fn set_span(&mut self, value: u32) {
+        self.span = value;
+    }
+}
+
+impl QueueHeader for TombstoneHeader {
+    fn head(&self) -> usize {
+        self.head as usize
+    }
+    fn set_head(&mut self, value: u32) {
+        self.head = value;
+    }
+    fn count(&self) -> usize {
+        self.count as usize
+    }
+    fn set_count(&mut self, value: u32) {
+        self.count = value;
+    }
+    fn incr_event_id(&mut self) {
+        self.seq_num += 1;
+    }
+    fn decr_event_id(&mut self, n: u64) -> Result<()> {
+        self.seq_num = self
+            .seq_num
+            .checked_sub(n)
+            .ok_or(QueueError::CorruptedQueue)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue<const N: usize>(len: u64) -> TombstoneEventQueue<N, u64>
+    where
+        Capacity<N>: TombstoneCapacity,
+    {
+        let mut queue = TombstoneEventQueue::<N, u64>::zeroed();
+        queue.init();
+        for i in 0..len {
+            queue.push_back(i).unwrap();
+        }
+        queue
+    }
+
+    #[test]
+    fn removals_leave_tombstones_until_compaction() {
+        let mut queue = queue::<8>(6);
+        assert_eq!(queue.remove_at(2).unwrap(), 2);
+        assert_eq!(queue.remove_at(3).unwrap(), 4);
+        assert_eq!((queue.span(), queue.tombstone_count()), (6, 2));
+        assert!(queue.iter().copied().eq([0, 1, 3, 5]));
+        assert!(queue.iter().rev().copied().eq([5, 3, 1, 0]));
+
+        // tombstones reaching an end are dropped with it
+        assert_eq!(queue.pop_back().unwrap(), 5);
+        assert_eq!((queue.span(), queue.tombstone_count()), (4, 1));
+        assert_eq!(queue.peek_back(), Some(&3));
+        assert_eq!(queue.remove_at(0).unwrap(), 0);
+        assert_eq!(queue.pop_front().unwrap(), 1);
+        assert_eq!((queue.span(), queue.tombstone_count()), (1, 0));
+        assert_eq!(queue.peek_front(), Some(&3));
+    }
+
+    #[test]
+    fn compacts_once_tombstones_outnumber_events() {
+        let mut queue = queue::<8>(7);
+        queue.remove_at(1).unwrap();
+        queue.remove_at(1).unwrap();
+        queue.remove_at(1).unwrap();
+        assert_eq!((queue.span(), queue.tombstone_count()), (7, 3));
+
+        // the fourth tombstone outnumbers the three events left
+        queue.remove_at(1).unwrap();
+        assert_eq!((queue.span(), queue.tombstone_count()), (3, 0));
+        assert!(queue.iter().copied().eq([0, 5, 6]));
+    }
+
+    #[test]
+    fn pops_compact_too() {
+        let mut queue = queue::<8>(7);
+        for _ in 0..3 {
+            queue.remove_at(1).unwrap();
+        }
+        assert_eq!(queue.pop_back().unwrap(), 6);
+        assert_eq!((queue.span(), queue.tombstone_count()), (6, 3));
+
+        // popping leaves two events behind three tombstones
+        assert_eq!(queue.pop_back().unwrap(), 5);
+        assert_eq!((queue.span(), queue.tombstone_count()), (2, 0));
+        assert!(queue.iter().copied().eq([0, 4]));
+    }
+
+    #[test]
+    fn push_compacts_a_full_span() {
+        let mut queue = queue::<8>(8);
+        queue.remove_at(1).unwrap();
+        assert_eq!((queue.span(), queue.tombstone_count()), (8, 1));
+
+        queue.push_back(8).unwrap();
+        assert_eq!((queue.span(), queue.tombstone_count()), (8, 0));
+        assert!(queue.iter().copied().eq([0, 2, 3, 4, 5, 6, 7, 8]));
+        assert_eq!(queue.push_back(9), Err(PushError::Full(9)));
+    }
+
+    #[test]
+    fn rejects_corrupted_header() {
+        let mut queue = queue::<8>(3);
+        queue.header.set_span(2);
+        assert_eq!(
+            queue.pop_front().unwrap_err(),
+            QueueError::CorruptedQueue.into()
+        );
+        queue.header.set_span(9);
+        assert_eq!(
+            queue.remove_at(0).unwrap_err(),
+            QueueError::CorruptedQueue.into()
+        );
+        assert_eq!(queue.iter().len(), 3);
+        assert_eq!(queue.iter().count(), 3);
+
+        // flags left on live events cannot cut the iteration short
+        queue.header.set_span(3);
+        queue.tombstones[1] = 1;
+        assert!(queue.iter().copied().eq([0, 1, 2]));
+        assert!(queue.iter().rev().copied().eq([2, 1, 0]));
+    }
+}
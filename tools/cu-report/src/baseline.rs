@@ -0,0 +1,219 @@
+use crate::parser::Measurement;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, error::Error, fmt, fs, path::Path};
+
+/// Committed CU cost of every `(instruction, section)` pair.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Baseline(BTreeMap<String, BTreeMap<String, u64>>);
+
+impl Baseline {
+    pub fn from_measurements(measurements: &[Measurement]) -> Self {
+        let mut baseline = Self::default();
+        for m in measurements {
+            baseline
+                .0
+                .entry(m.instruction.clone())
+                .or_default()
+                .insert(m.section.clone(), m.units);
+        }
+        baseline
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let data = fs::read(path).map_err(|err| {
+            format!(
+                "cannot read baseline {}: {err} (record one with --save-baseline)",
+                path.display()
+            )
+        })?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut data = serde_json::to_string_pretty(self)?;
+        data.push('\n');
+        fs::write(path, data)?;
+        Ok(())
+    }
+
+    pub fn get(&self, instruction: &str, section: &str) -> Option<u64> {
+        self.0.get(instruction)?.get(section).copied()
+    }
+
+    /// Compares a run against the baseline, collecting every section that got more
+    /// expensive than `thresholds` allow and every baseline section the run did not produce.
+    pub fn check(&self, measurements: &[Measurement], thresholds: &Thresholds) -> Check {
+        let mut check = Check::default();
+        for m in measurements {
+            match self.get(&m.instruction, &m.section) {
+                Some(baseline) if thresholds.is_regression(baseline, m.units) => {
+                    check.regressions.push(Regression {
+                        instruction: m.instruction.clone(),
+                        section: m.section.clone(),
+                        baseline,
+                        current: m.units,
+                    })
+                }
+                Some(_) => {}
+                None => check.added.push((m.instruction.clone(), m.section.clone())),
+            }
+        }
+
+        let current = Self::from_measurements(measurements);
+        for (instruction, sections) in &self.0 {
+            for section in sections.keys() {
+                if current.get(instruction, section).is_none() {
+                    check.missing.push((instruction.clone(), section.clone()));
+                }
+            }
+        }
+        check
+    }
+}
+
+/// Allowed CU increase per section before it counts as a regression.
+///
+/// A section regresses when it exceeds either configured limit. Without any limit, every
+/// increase is a regression: compute unit consumption is deterministic.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    pub absolute: Option<u64>,
+    pub percent: Option<f64>,
+}
+
+impl Thresholds {
+    pub fn is_regression(&self, baseline: u64, current: u64) -> bool {
+        if current <= baseline {
+            return false;
+        }
+        let increase = current - baseline;
+        let over_absolute = self.absolute.map(|limit| increase > limit);
+        let over_percent = self
+            .percent
+            .map(|limit| increase as f64 * 100.0 > limit * baseline as f64);
+
+        match (over_absolute, over_percent) {
+            (None, None) => true,
+            (a, p) => a.unwrap_or(false) || p.unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Regression {
+    pub instruction: String,
+    pub section: String,
+    pub baseline: u64,
+    pub current: u64,
+}
+
+impl fmt::Display for Regression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let increase = self.current - self.baseline;
+        write!(
+            f,
+            "{} {}: {} -> {} (+{}",
+            self.instruction, self.section, self.baseline, self.current, increase
+        )?;
+        if self.baseline > 0 {
+            write!(
+                f,
+                ", +{:.1}%",
+                increase as f64 * 100.0 / self.baseline as f64
+            )?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Outcome of [`Baseline::check`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub regressions: Vec<Regression>,
+    /// Sections present in the baseline but absent from the run.
+    pub missing: Vec<(String, String)>,
+    /// Sections of the run that have no baseline yet.
+    pub added: Vec<(String, String)>,
+}
+
+impl Check {
+    pub fn passed(&self) -> bool {
+        self.regressions.is_empty() && self.missing.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn baseline() -> Baseline {
+        Baseline::from_measurements(&[
+            Measurement::new("RingBuf", "Inserting_488", 100_000),
+            Measurement::new("DLList", "Inserting_488", 120_000),
+        ])
+    }
+
+    #[test]
+    fn any_increase_regresses_without_thresholds() {
+        let thresholds = Thresholds::default();
+        assert!(!thresholds.is_regression(1000, 1000));
+        assert!(!thresholds.is_regression(1000, 900));
+        assert!(thresholds.is_regression(1000, 1001));
+    }
+
+    #[test]
+    fn either_threshold_flags_regression() {
+        let thresholds = Thresholds {
+            absolute: Some(500),
+            percent: Some(10.0),
+        };
+        assert!(!thresholds.is_regression(1000, 1100));
+        assert!(thresholds.is_regression(1000, 1101));
+        assert!(thresholds.is_regression(100_000, 100_501));
+    }
+
+    #[test]
+    fn detects_doubled_push_back() {
+        let run = [
+            Measurement::new("RingBuf", "Inserting_488", 100_000),
+            Measurement::new("DLList", "Inserting_488", 240_000),
+        ];
+        let check = baseline().check(&run, &Thresholds::default());
+        assert!(!check.passed());
+        assert_eq!(
+            check.regressions,
+            vec![Regression {
+                instruction: "DLList".into(),
+                section: "Inserting_488".into(),
+                baseline: 120_000,
+                current: 240_000,
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_missing_and_added_sections() {
+        let run = [
+            Measurement::new("RingBuf", "Inserting_488", 100_000),
+            Measurement::new("DLList", "Inserting_500", 120_000),
+        ];
+        let check = baseline().check(&run, &Thresholds::default());
+        assert!(!check.passed());
+        assert_eq!(
+            check.missing,
+            vec![("DLList".to_string(), "Inserting_488".to_string())]
+        );
+        assert_eq!(
+            check.added,
+            vec![("DLList".to_string(), "Inserting_500".to_string())]
+        );
+    }
+
+    #[test]
+    fn json_is_keyed_by_instruction_and_section() {
+        let json = serde_json::to_value(baseline()).unwrap();
+        assert_eq!(json["DLList"]["Inserting_488"], 120_000);
+        assert_eq!(json["RingBuf"]["Inserting_488"], 100_000);
+    }
+}
@@ -0,0 +1,218 @@
+use anchor_lang::InstructionData;
+use cu_report::{
+    calibration,
+    matrix::{self, Matrix, Row},
+    LogParser, Measurement,
+};
+use openbook_v2_cu::QueueKind;
+use solana_program_test::{tokio, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    compute_budget::ComputeBudgetInstruction,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Signer,
+    transaction::Transaction,
+};
+use std::{error::Error, fs, path::PathBuf, process::exit, str::FromStr};
+
+const USAGE: &str = "usage: cu-matrix [OPTIONS]
+
+Runs the isolated queue operations of openbook-v2-cu under solana-program-test across a
+matrix of backends, capacities, fill levels, removal counts and iteration lengths, and
+writes the calibrated cost of every operation as a long-format table to matrix.csv in the
+output directory. The program must have been built with `cargo build-sbf` beforehand.
+
+options:
+    --out-dir <DIR>          report directory (default: target/cu-report)
+    --kinds <LIST>           backends among RingBuf,DLList,Tombstone (default: all)
+    --capacities <LIST>      queue capacities (default: 64,256,488,1024,2048,4096)
+    --item-size <BYTES>      item size (default: 200)
+    --fills <LIST>           fill levels in percent of the capacity (default: 25,50,100)
+    --removals <LIST>        random removal counts (default: 1,10,50)
+    --iterations <LIST>      iteration lengths (default: 1,10,100)
+    --seed <SEED>            seed of the removed positions (default: 0)";
+
+/// Compute unit limit requested by every transaction.
+const COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+struct Options {
+    out_dir: PathBuf,
+    matrix: Matrix,
+}
+
+fn parse_list<T: FromStr>(list: &str) -> Result<Vec<T>, String> {
+    list.split(',')
+        .map(|item| {
+            item.trim()
+                .parse()
+                .map_err(|_| format!("invalid list item `{item}`"))
+        })
+        .collect()
+}
+
+fn parse_kind(kind: &str) -> Result<QueueKind, String> {
+    QueueKind::ALL
+        .into_iter()
+        .find(|k| k.name() == kind.trim())
+        .ok_or_else(|| format!("unknown backend `{kind}`"))
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        out_dir: PathBuf::from(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../target/cu-report"
+        )),
+        matrix: Matrix::default(),
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} requires a value"));
+        let matrix = &mut options.matrix;
+        match arg.as_str() {
+            "--out-dir" => options.out_dir = value()?.into(),
+            "--kinds" => {
+                matrix.kinds = value()?
+                    .split(',')
+                    .map(parse_kind)
+                    .collect::<Result<_, _>>()?
+            }
+            "--capacities" => matrix.capacities = parse_list(&value()?)?,
+            "--item-size" => {
+                let size = value()?;
+                matrix.item_size = size
+                    .parse()
+                    .map_err(|_| format!("invalid item size `{size}`"))?;
+            }
+            "--fills" => matrix.fills = parse_list(&value()?)?,
+            "--removals" => matrix.removals = parse_list(&value()?)?,
+            "--iterations" => matrix.iterations = parse_list(&value()?)?,
+            "--seed" => {
+                let seed = value()?;
+                matrix.seed = seed.parse().map_err(|_| format!("invalid seed `{seed}`"))?;
+            }
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                exit(0);
+            }
+            _ => return Err(format!("unexpected argument `{arg}`")),
+        }
+    }
+    Ok(options)
+}
+
+#[tokio::main]
+async fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}\n\n{USAGE}");
+        exit(2);
+    });
+
+    if let Err(err) = run(&options).await {
+        eprintln!("cu-matrix: {err}");
+        exit(1);
+    }
+}
+
+async fn run(options: &Options) -> Result<(), Box<dyn Error>> {
+    // program-test looks for openbook_v2_cu.so in the SBF output directory
+    let deploy_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../../target/deploy");
+    for var in ["SBF_OUT_DIR", "BPF_OUT_DIR"] {
+        if std::env::var_os(var).is_none() {
+            std::env::set_var(var, deploy_dir);
+        }
+    }
+
+    let mut program = ProgramTest::default();
+    program.prefer_bpf(true);
+    program.add_program("openbook_v2_cu", openbook_v2_cu::id(), None);
+
+    let mut queues = Vec::new();
+    for layout in options.matrix.layouts() {
+        let len = layout
+            .account_len()
+            .ok_or_else(|| format!("unsupported queue layout {layout:?}"))?;
+        let pubkey = Pubkey::new_unique();
+        program.add_account(pubkey, zero_account(len));
+        queues.push((layout, pubkey));
+    }
+
+    let mut runner = Runner {
+        context: program.start_with_context().await,
+        nonce: 0,
+    };
+
+    let calibration = runner
+        .send(openbook_v2_cu::instruction::Calibrate {}.data(), vec![])
+        .await?;
+    let overhead = calibration::overhead(&calibration)
+        .ok_or("the calibrate instruction logged no calibration section")?;
+    println!("calibrated measurement overhead: {overhead} CU\n");
+
+    let mut rows = Vec::new();
+    for (layout, pubkey) in queues {
+        for step in options.matrix.steps(&layout) {
+            let accounts = vec![AccountMeta::new(pubkey, false)];
+            let mut measurements = runner.send(step.data(layout), accounts).await?;
+            calibration::apply(&mut measurements, overhead);
+            rows.extend(measurements.iter().filter_map(Row::from_measurement));
+        }
+    }
+
+    fs::create_dir_all(&options.out_dir)?;
+    let path = options.out_dir.join(matrix::CSV_MATRIX);
+    matrix::write_csv(fs::File::create(&path)?, &rows)?;
+    print!("{}", matrix::render(&rows));
+    println!("\nmatrix written to {}", path.display());
+    Ok(())
+}
+
+fn zero_account(len: usize) -> Account {
+    Account {
+        owner: openbook_v2_cu::id(),
+        lamports: Rent::default().minimum_balance(len),
+        data: vec![0; len],
+        ..Account::default()
+    }
+}
+
+struct Runner {
+    context: ProgramTestContext,
+    nonce: u32,
+}
+
+impl Runner {
+    /// Executes one instruction and returns the measurements parsed from its logs.
+    async fn send(
+        &mut self,
+        data: Vec<u8>,
+        accounts: Vec<AccountMeta>,
+    ) -> Result<Vec<Measurement>, Box<dyn Error>> {
+        // a distinct limit per transaction keeps repeated instructions, e.g. resetting a
+        // queue for the next fill level, from being rejected as already processed
+        self.nonce += 1;
+        let limit =
+            ComputeBudgetInstruction::set_compute_unit_limit(COMPUTE_UNIT_LIMIT - self.nonce);
+        let ix = Instruction::new_with_bytes(openbook_v2_cu::id(), &data, accounts);
+        let tx = Transaction::new_signed_with_payer(
+            &[limit, ix],
+            Some(&self.context.payer.pubkey()),
+            &[&self.context.payer],
+            self.context.last_blockhash,
+        );
+
+        let result = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(tx)
+            .await?;
+        result.result?;
+
+        let mut parser = LogParser::new();
+        let logs = result.metadata.map(|m| m.log_messages).unwrap_or_default();
+        Ok(logs.iter().filter_map(|l| parser.push_line(l)).collect())
+    }
+}
@@ -0,0 +1,62 @@
+use crate::parser::Measurement;
+pub use openbook_v2_cu::cu::CALIBRATION_SECTION;
+
+/// Instruction name logged by `openbook_v2_cu::calibrate`.
+pub const CALIBRATE: &str = "Calibrate";
+
+/// Measurement overhead calibrated from the empty sections of the `calibrate` instruction.
+///
+/// An empty section costs the closing `sol_log_compute_units` syscall plus whatever the
+/// guard executes between the two readings, which is exactly what every other section
+/// pays on top of its body. The median raw delta is used so a stray reading cannot skew it.
+pub fn overhead(measurements: &[Measurement]) -> Option<u64> {
+    let mut samples: Vec<u64> = measurements
+        .iter()
+        .filter(|m| m.instruction == CALIBRATE && m.section == CALIBRATION_SECTION)
+        .map(|m| m.raw)
+        .collect();
+    if samples.is_empty() {
+        return None;
+    }
+    samples.sort_unstable();
+    Some(samples[samples.len() / 2])
+}
+
+/// Subtracts the calibrated `overhead` from every measurement instead of the fixed
+/// syscall cost announced by the program.
+pub fn apply(measurements: &mut [Measurement], overhead: u64) {
+    for m in measurements {
+        m.set_overhead(overhead);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_of_empty_sections() {
+        let measurements = [
+            Measurement::new(CALIBRATE, CALIBRATION_SECTION, 104),
+            Measurement::new(CALIBRATE, CALIBRATION_SECTION, 103),
+            Measurement::new(CALIBRATE, CALIBRATION_SECTION, 150),
+            Measurement::new("RingBuf", "Iterating_10", 900),
+        ];
+        assert_eq!(overhead(&measurements), Some(104));
+        assert_eq!(overhead(&measurements[3..]), None);
+    }
+
+    #[test]
+    fn corrects_every_section_and_keeps_raw() {
+        let mut measurements = [
+            Measurement::new(CALIBRATE, CALIBRATION_SECTION, 103),
+            Measurement::new("RingBuf", "Iterating_10", 900),
+        ];
+        apply(&mut measurements, 103);
+
+        assert_eq!(measurements[0].units, 0);
+        assert_eq!(measurements[1].raw, 900);
+        assert_eq!(measurements[1].overhead, 103);
+        assert_eq!(measurements[1].units, 797);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{parser::Measurement, table};
+use serde::Serialize;
+use solana_program::rent::Rent;
+use std::mem::size_of;
+
+/// Benchmark name logged by `openbook_v2_cu::ring_buf`, suffixed with the queue layout.
+pub const RING_BUF: &str = "RingBuf";
+/// Benchmark name logged by `openbook_v2_cu::d_l_list`, suffixed with the queue layout.
+pub const DL_LIST: &str = "DLList";
+
+/// Cost of one section label in both compared instructions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SectionComparison {
+    pub section: String,
+    pub left: Option<u64>,
+    pub right: Option<u64>,
+}
+
+impl SectionComparison {
+    /// `right - left`, when both sides ran the section.
+    pub fn difference(&self) -> Option<i64> {
+        Some(self.right? as i64 - self.left? as i64)
+    }
+
+    /// Difference relative to the left side, in percent.
+    pub fn relative(&self) -> Option<f64> {
+        let left = self.left.filter(|&l| l > 0)?;
+        Some(self.difference()? as f64 * 100.0 / left as f64)
+    }
+}
+
+/// On-chain footprint of a zero-copy account, including its 8 byte discriminator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Footprint {
+    pub account: String,
+    pub size: usize,
+    pub rent: u64,
+}
+
+impl Footprint {
+    pub fn of<T>(account: &str) -> Self {
+        let size = 8 + size_of::<T>();
+        Self {
+            account: account.to_string(),
+            size,
+            rent: Rent::default().minimum_balance(size),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Comparison {
+    pub left: String,
+    pub right: String,
+    pub sections: Vec<SectionComparison>,
+    pub footprints: Vec<Footprint>,
+}
+
+impl Comparison {
+    /// Aligns the sections of two instructions by label, in order of first appearance.
+    pub fn new(measurements: &[Measurement], left: &str, right: &str) -> Self {
+        let mut sections: Vec<SectionComparison> = Vec::new();
+        for m in measurements {
+            let is_left = m.instruction == left;
+            if !is_left && m.instruction != right {
+                continue;
+            }
+
+            let index = match sections.iter().position(|s| s.section == m.section) {
+                Some(index) => index,
+                None => {
+                    sections.push(SectionComparison {
+                        section: m.section.clone(),
+                        left: None,
+                        right: None,
+                    });
+                    sections.len() - 1
+                }
+            };
+            if is_left {
+                sections[index].left = Some(m.units);
+            } else {
+                sections[index].right = Some(m.units);
+            }
+        }
+
+        Self {
+            left: left.to_string(),
+            right: right.to_string(),
+            sections,
+            footprints: Vec::new(),
+        }
+    }
+
+    /// The ring buffer against the doubly linked list of events at the default capacity, with
+    /// both account footprints.
+    pub fn event_queues(measurements: &[Measurement]) -> Self {
+        use openbook_v2_cu::{
+            state::{DLLEventQueue, EventQueue, MAX_NUM_EVENTS},
+            EVENT_SIZE,
+        };
+
+        let layout = format!("{MAX_NUM_EVENTS}_{EVENT_SIZE}");
+        let mut comparison = Self::new(
+            measurements,
+            &format!("{RING_BUF}_{layout}"),
+            &format!("{DL_LIST}_{layout}"),
+        );
+        comparison.footprints = vec![
+            Footprint::of::<EventQueue>("EventQueue"),
+            Footprint::of::<DLLEventQueue>("DLLEventQueue"),
+        ];
+        comparison
+    }
+
+    pub fn render(&self) -> String {
+        let cell = |v: Option<u64>| v.map_or_else(|| "-".to_string(), |v| v.to_string());
+
+        let mut rows = vec![vec![
+            "section".to_string(),
+            self.left.clone(),
+            self.right.clone(),
+            "diff".to_string(),
+            "diff %".to_string(),
+        ]];
+        for s in &self.sections {
+            rows.push(vec![
+                s.section.clone(),
+                cell(s.left),
+                cell(s.right),
+                s.difference()
+                    .map_or_else(|| "-".to_string(), |d| format!("{d:+}")),
+                s.relative()
+                    .map_or_else(|| "-".to_string(), |r| format!("{r:+.1}%")),
+            ]);
+        }
+        let mut out = table::align(&rows, 1);
+
+        if !self.footprints.is_empty() {
+            let mut rows = vec![vec![
+                "account".to_string(),
+                "size".to_string(),
+                "rent".to_string(),
+            ]];
+            for f in &self.footprints {
+                rows.push(vec![
+                    f.account.clone(),
+                    f.size.to_string(),
+                    f.rent.to_string(),
+                ]);
+            }
+            out.push('\n');
+            out.push_str(&table::align(&rows, 1));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aligns_sections_by_label() {
+        let measurements = [
+            Measurement::new(RING_BUF, "Inserting_488", 100_000),
+            Measurement::new(RING_BUF, "Iterating_10", 400),
+            Measurement::new(DL_LIST, "Initialize", 9_000),
+            Measurement::new(DL_LIST, "Inserting_488", 125_000),
+            Measurement::new(DL_LIST, "Iterating_10", 300),
+        ];
+        let comparison = Comparison::new(&measurements, RING_BUF, DL_LIST);
+
+        assert_eq!(comparison.sections.len(), 3);
+        let inserting = &comparison.sections[0];
+        assert_eq!(inserting.section, "Inserting_488");
+        assert_eq!(inserting.difference(), Some(25_000));
+        assert_eq!(inserting.relative(), Some(25.0));
+
+        let iterating = &comparison.sections[1];
+        assert_eq!(iterating.difference(), Some(-100));
+        assert_eq!(iterating.relative(), Some(-25.0));
+
+        let initialize = &comparison.sections[2];
+        assert_eq!(initialize.left, None);
+        assert_eq!(initialize.right, Some(9_000));
+        assert_eq!(initialize.difference(), None);
+    }
+
+    #[test]
+    fn footprint_includes_discriminator() {
+        let footprint = Footprint::of::<[u64; 4]>("Test");
+        assert_eq!(footprint.size, 40);
+        assert_eq!(footprint.rent, Rent::default().minimum_balance(40));
+    }
+}
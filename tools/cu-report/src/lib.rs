@@ -0,0 +1,9 @@
+pub mod baseline;
+pub mod calibration;
+pub mod compare;
+pub mod matrix;
+pub mod parser;
+pub mod report;
+pub mod table;
+
+pub use parser::{parse_log, LogParser, Measurement};
@@ -0,0 +1,176 @@
+use cu_report::{
+    baseline::{Baseline, Thresholds},
+    calibration,
+    compare::Comparison,
+    report, table, LogParser, Measurement,
+};
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::PathBuf,
+    process::{exit, Command, Stdio},
+};
+
+const USAGE: &str = "usage: cu-report [OPTIONS]
+
+Runs `cargo test-sbf` on openbook-v2-cu and prints the compute units spent in each
+benchmark section, both raw and corrected by the overhead calibrated from the
+`calibrate` instruction, followed by a side-by-side comparison of the ring buffer and the
+doubly linked list. report.json, report.csv and comparison.json are written to the
+output directory.
+
+options:
+    --log <FILE>              parse a previously captured test log instead of running tests
+    --out-dir <DIR>           report directory (default: target/cu-report)
+    --baseline <FILE>         fail if any section regressed against this baseline
+    --save-baseline <FILE>    record the run as the new baseline
+    --max-increase <UNITS>    tolerated absolute CU increase per section
+    --max-increase-pct <PCT>  tolerated relative CU increase per section";
+
+struct Options {
+    log: Option<PathBuf>,
+    out_dir: PathBuf,
+    baseline: Option<PathBuf>,
+    save_baseline: Option<PathBuf>,
+    thresholds: Thresholds,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        log: None,
+        out_dir: PathBuf::from(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../target/cu-report"
+        )),
+        baseline: None,
+        save_baseline: None,
+        thresholds: Thresholds::default(),
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} requires a value"));
+        match arg.as_str() {
+            "--log" => options.log = Some(value()?.into()),
+            "--out-dir" => options.out_dir = value()?.into(),
+            "--baseline" => options.baseline = Some(value()?.into()),
+            "--save-baseline" => options.save_baseline = Some(value()?.into()),
+            "--max-increase" => {
+                let units = value()?;
+                let units = units
+                    .parse()
+                    .map_err(|_| format!("invalid unit count `{units}`"))?;
+                options.thresholds.absolute = Some(units);
+            }
+            "--max-increase-pct" => {
+                let percent = value()?;
+                let percent = percent
+                    .parse()
+                    .map_err(|_| format!("invalid percentage `{percent}`"))?;
+                options.thresholds.percent = Some(percent);
+            }
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                exit(0);
+            }
+            _ => return Err(format!("unexpected argument `{arg}`")),
+        }
+    }
+    Ok(options)
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}\n\n{USAGE}");
+        exit(2);
+    });
+
+    match run(&options) {
+        Ok(true) => {}
+        Ok(false) => exit(1),
+        Err(err) => {
+            eprintln!("cu-report: {err}");
+            exit(1);
+        }
+    }
+}
+
+/// Produces the reports and returns whether the run passed the baseline check.
+fn run(options: &Options) -> Result<bool, Box<dyn Error>> {
+    let mut measurements = match &options.log {
+        Some(path) => collect(BufReader::new(File::open(path)?), false)?,
+        None => run_tests()?,
+    };
+
+    match calibration::overhead(&measurements) {
+        Some(overhead) => {
+            calibration::apply(&mut measurements, overhead);
+            println!("calibrated measurement overhead: {overhead} CU\n");
+        }
+        None => eprintln!("no calibration sections found, subtracting the fixed syscall cost"),
+    }
+
+    let comparison = Comparison::event_queues(&measurements);
+    report::write_reports(&options.out_dir, &measurements, &comparison)?;
+    print!("{}", table::render(&measurements));
+    print!("\n{}", comparison.render());
+
+    if let Some(path) = &options.save_baseline {
+        Baseline::from_measurements(&measurements).save(path)?;
+        println!("\nbaseline saved to {}", path.display());
+    }
+
+    let Some(path) = &options.baseline else {
+        return Ok(true);
+    };
+    let check = Baseline::load(path)?.check(&measurements, &options.thresholds);
+    for (instruction, section) in &check.added {
+        println!("new section without baseline: {instruction} {section}");
+    }
+    for (instruction, section) in &check.missing {
+        eprintln!("missing section: {instruction} {section}");
+    }
+    for regression in &check.regressions {
+        eprintln!("regression: {regression}");
+    }
+    Ok(check.passed())
+}
+
+/// Runs the program tests, echoing their log output while it is parsed.
+///
+/// The tests run one at a time, as the parser pairs each reading with the section last
+/// opened and the logs of parallel tests would interleave.
+fn run_tests() -> Result<Vec<Measurement>, Box<dyn Error>> {
+    let manifest = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../programs/openbook-v2-cu/Cargo.toml"
+    );
+    let mut child = Command::new("cargo")
+        .args(["test-sbf", "--manifest-path", manifest])
+        .env("RUST_TEST_THREADS", "1")
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let measurements = collect(BufReader::new(stderr), true)?;
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(format!("cargo test-sbf failed: {status}").into());
+    }
+    Ok(measurements)
+}
+
+fn collect(reader: impl BufRead, echo: bool) -> io::Result<Vec<Measurement>> {
+    let mut parser = LogParser::new();
+    let mut measurements = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if echo {
+            eprintln!("{line}");
+        }
+        measurements.extend(parser.push_line(&line));
+    }
+    Ok(measurements)
+}
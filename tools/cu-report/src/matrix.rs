@@ -0,0 +1,298 @@
+use crate::{parser::Measurement, table};
+use anchor_lang::InstructionData;
+use openbook_v2_cu::{
+    bench::{random_positions, Rng},
+    instruction, QueueKind, QueueLayout, CAPACITIES, EVENT_SIZE,
+};
+use serde::Serialize;
+use std::io::Write;
+
+pub const CSV_MATRIX: &str = "matrix.csv";
+
+/// Dimensions swept by the `cu-matrix` runner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Matrix {
+    pub kinds: Vec<QueueKind>,
+    pub capacities: Vec<usize>,
+    pub item_size: usize,
+    /// Fill levels, in percent of the capacity.
+    pub fills: Vec<usize>,
+    pub removals: Vec<usize>,
+    pub iterations: Vec<usize>,
+    pub seed: u64,
+}
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Self {
+            kinds: QueueKind::ALL.to_vec(),
+            capacities: CAPACITIES.to_vec(),
+            item_size: EVENT_SIZE,
+            fills: vec![25, 50, 100],
+            removals: vec![1, 10, 50],
+            iterations: vec![1, 10, 100],
+            seed: 0,
+        }
+    }
+}
+
+impl Matrix {
+    /// One persisted queue per backend and capacity.
+    pub fn layouts(&self) -> Vec<QueueLayout> {
+        let mut layouts = Vec::new();
+        for &kind in &self.kinds {
+            for &capacity in &self.capacities {
+                layouts.push(QueueLayout {
+                    kind,
+                    capacity: capacity as u32,
+                    item_size: self.item_size as u32,
+                });
+            }
+        }
+        layouts
+    }
+
+    /// Instructions sent to the queue of `layout`.
+    ///
+    /// For every fill level the queue is reset and filled, iterated over every length from
+    /// both ends, hit by every removal count and refilled after each one, then drained from
+    /// the back, refilled and drained from the front. Lengths and counts exceeding the fill
+    /// level are skipped.
+    pub fn steps(&self, layout: &QueueLayout) -> Vec<Step> {
+        let mut rng = Rng::new(self.seed);
+        let mut steps = Vec::new();
+        for &percent in &self.fills {
+            let fill = layout.capacity as usize * percent / 100;
+            steps.push(Step::Init);
+            if fill == 0 {
+                continue;
+            }
+
+            steps.push(Step::Push(fill as u32));
+            for &n in self.iterations.iter().filter(|&&n| n <= fill) {
+                steps.push(Step::Iterate(n as u32));
+                steps.push(Step::IterateBack(n as u32));
+            }
+            for &removals in self.removals.iter().filter(|&&r| r <= fill) {
+                let positions = random_positions(&mut rng, fill, removals);
+                steps.push(Step::RemoveAt(
+                    positions.into_iter().map(|pos| pos as u32).collect(),
+                ));
+                steps.push(Step::Push(removals as u32));
+            }
+            steps.push(Step::PopBack(fill as u32));
+            steps.push(Step::Push(fill as u32));
+            steps.push(Step::Pop(fill as u32));
+        }
+        steps
+    }
+}
+
+/// One isolated operation instruction of `openbook_v2_cu`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Init,
+    Push(u32),
+    Pop(u32),
+    PopBack(u32),
+    RemoveAt(Vec<u32>),
+    Iterate(u32),
+    IterateBack(u32),
+}
+
+impl Step {
+    /// Instruction data of the step on a queue of `layout`.
+    pub fn data(&self, layout: QueueLayout) -> Vec<u8> {
+        match self {
+            Self::Init => instruction::InitQueue { layout }.data(),
+            Self::Push(n) => instruction::PushN { layout, n: *n }.data(),
+            Self::Pop(n) => instruction::PopN { layout, n: *n }.data(),
+            Self::PopBack(n) => instruction::PopBackN { layout, n: *n }.data(),
+            Self::RemoveAt(positions) => instruction::RemoveAt {
+                layout,
+                positions: positions.clone(),
+            }
+            .data(),
+            Self::Iterate(n) => instruction::IterateN { layout, n: *n }.data(),
+            Self::IterateBack(n) => instruction::IterateBackN { layout, n: *n }.data(),
+        }
+    }
+}
+
+/// Cost of one isolated operation, a row of the long-format matrix table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Row {
+    pub backend: String,
+    pub capacity: usize,
+    pub item_size: usize,
+    /// Queue length before the operation.
+    pub fill: usize,
+    pub operation: String,
+    /// Number of items the operation pushed, popped, removed or visited.
+    pub n: usize,
+    pub units: u64,
+}
+
+impl Row {
+    /// Parses a measurement reported as `<backend>_<capacity>_<item size>` with a
+    /// `<operation>_<n>_at_<fill>` section. Other measurements yield `None`.
+    pub fn from_measurement(m: &Measurement) -> Option<Self> {
+        let mut name = m.instruction.rsplitn(3, '_');
+        let item_size = name.next()?.parse().ok()?;
+        let capacity = name.next()?.parse().ok()?;
+        let backend = name.next()?.to_string();
+
+        let (operation, rest) = m.section.split_once('_')?;
+        let (n, fill) = rest.split_once("_at_")?;
+        Some(Self {
+            backend,
+            capacity,
+            item_size,
+            fill: fill.parse().ok()?,
+            operation: operation.to_string(),
+            n: n.parse().ok()?,
+            units: m.units,
+        })
+    }
+}
+
+pub fn write_csv(writer: impl Write, rows: &[Row]) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn render(rows: &[Row]) -> String {
+    let mut table = vec![[
+        "backend",
+        "capacity",
+        "item_size",
+        "fill",
+        "operation",
+        "n",
+        "units",
+    ]
+    .map(String::from)
+    .to_vec()];
+    for row in rows {
+        table.push(vec![
+            row.backend.clone(),
+            row.capacity.to_string(),
+            row.item_size.to_string(),
+            row.fill.to_string(),
+            row.operation.clone(),
+            row.n.to_string(),
+            row.units.to_string(),
+        ]);
+    }
+    table::align(&table, 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_isolated_operations() {
+        let row = Row::from_measurement(&Measurement::new(
+            "DLList_488_200",
+            "RemoveAt_10_at_244",
+            900,
+        ));
+        assert_eq!(
+            row,
+            Some(Row {
+                backend: "DLList".into(),
+                capacity: 488,
+                item_size: 200,
+                fill: 244,
+                operation: "RemoveAt".into(),
+                n: 10,
+                units: 900,
+            })
+        );
+        assert_eq!(
+            Row::from_measurement(&Measurement::new("DLList_488_200", "Initialize", 900)),
+            None
+        );
+        assert_eq!(
+            Row::from_measurement(&Measurement::new("Calibrate", "Empty", 100)),
+            None
+        );
+    }
+
+    #[test]
+    fn steps_stay_within_the_fill_level() {
+        let matrix = Matrix {
+            fills: vec![0, 25],
+            removals: vec![1, 20],
+            iterations: vec![16, 17],
+            ..Matrix::default()
+        };
+        let layout = QueueLayout {
+            kind: QueueKind::RingBuf,
+            capacity: 64,
+            item_size: EVENT_SIZE as u32,
+        };
+
+        let steps = matrix.steps(&layout);
+        assert_eq!(steps.len(), 10);
+        assert_eq!(
+            steps[..5],
+            [
+                Step::Init,
+                Step::Init,
+                Step::Push(16),
+                Step::Iterate(16),
+                Step::IterateBack(16)
+            ]
+        );
+        assert!(matches!(&steps[5], Step::RemoveAt(positions) if positions.len() == 1));
+        assert_eq!(
+            steps[6..],
+            [
+                Step::Push(1),
+                Step::PopBack(16),
+                Step::Push(16),
+                Step::Pop(16)
+            ]
+        );
+        assert_eq!(matrix.steps(&layout), steps);
+    }
+
+    /// Sends every step natively through the program entrypoint, each layout reusing one
+    /// account across fill levels like the runner does.
+    #[test]
+    fn steps_run_on_one_account_per_layout() {
+        use anchor_lang::prelude::{AccountInfo, Pubkey};
+
+        let matrix = Matrix::default();
+        let program_id = openbook_v2_cu::id();
+        for layout in matrix.layouts() {
+            let key = Pubkey::new_unique();
+            let mut lamports = 0;
+            let mut data = vec![0; layout.account_len().unwrap()];
+            let account = AccountInfo::new(
+                &key,
+                false,
+                true,
+                &mut lamports,
+                &mut data,
+                &program_id,
+                false,
+                0,
+            );
+            for step in matrix.steps(&layout) {
+                openbook_v2_cu::entry(
+                    &program_id,
+                    std::slice::from_ref(&account),
+                    &step.data(layout),
+                )
+                .unwrap_or_else(|err| panic!("{:?} on {:?}: {}", step, layout, err));
+            }
+        }
+    }
+}
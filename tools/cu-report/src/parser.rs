@@ -0,0 +1,304 @@
+use serde::{Deserialize, Serialize};
+
+const PROGRAM_LOG: &str = "Program log: ";
+const INSTRUCTION: &str = "Instruction: ";
+const CONSUMPTION: &str = "Program consumption: ";
+const BENCH: &str = "#bench ";
+const BEGIN: &str = "#begin ";
+const END: &str = "#end";
+const SECTION: &str = "#";
+
+/// Compute units spent by one measured section of a benchmark instruction.
+///
+/// `before` and `after` are the remaining-budget readings logged by
+/// `sol_log_compute_units()` around the section and `raw` is their difference. `units` is
+/// `raw` minus the measurement `overhead`, which is the fixed cost announced by the
+/// section's end marker until replaced by a calibrated value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Measurement {
+    pub instruction: String,
+    pub section: String,
+    pub before: u64,
+    pub after: u64,
+    pub raw: u64,
+    pub overhead: u64,
+    pub units: u64,
+}
+
+impl Measurement {
+    /// Replaces the subtracted measurement overhead.
+    pub fn set_overhead(&mut self, overhead: u64) {
+        self.overhead = overhead;
+        self.units = self.raw.saturating_sub(overhead);
+    }
+}
+
+#[cfg(test)]
+impl Measurement {
+    /// Section reading `raw` units, with no overhead subtracted yet.
+    pub fn new(instruction: &str, section: &str, raw: u64) -> Self {
+        Self {
+            instruction: instruction.into(),
+            section: section.into(),
+            before: 1_400_000,
+            after: 1_400_000 - raw,
+            raw,
+            overhead: 0,
+            units: raw,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Frame {
+    label: String,
+    before: Option<u64>,
+    after: Option<u64>,
+    /// Opened by `#begin` and closed by `#end`, as opposed to a plain `# <label>` section
+    /// which is closed by its second reading.
+    scoped: bool,
+}
+
+/// Incremental parser over the log stream of `cargo test-sbf`.
+///
+/// Lines are fed one at a time so the parser can sit behind a running test process. Sections
+/// emitted by `cu_section!` are delimited by `#begin <label>` and `#end <overhead>` markers
+/// and may nest. A plain `# <label>` log also starts a section, which is closed by the
+/// second `sol_log_compute_units()` reading that follows it. A `#bench <name>` log renames
+/// the instruction for the measurements that follow.
+#[derive(Debug, Default)]
+pub struct LogParser {
+    instruction: Option<String>,
+    frames: Vec<Frame>,
+}
+
+impl LogParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consumes a single log line, returning a measurement once a section is complete.
+    pub fn push_line(&mut self, line: &str) -> Option<Measurement> {
+        if let Some(message) = after(line, PROGRAM_LOG) {
+            return self.push_message(message);
+        }
+
+        let remaining = parse_consumption(line)?;
+        let frame = self.frames.last_mut()?;
+        if frame.before.is_none() {
+            frame.before = Some(remaining);
+        } else if frame.after.is_none() {
+            frame.after = Some(remaining);
+            if !frame.scoped {
+                return self.close(0);
+            }
+        }
+        None
+    }
+
+    fn push_message(&mut self, message: &str) -> Option<Measurement> {
+        if let Some(instruction) = message
+            .strip_prefix(INSTRUCTION)
+            .or_else(|| message.strip_prefix(BENCH))
+        {
+            self.instruction = Some(instruction.trim().to_string());
+            self.frames.clear();
+        } else if let Some(label) = message.strip_prefix(BEGIN) {
+            self.open(label, true);
+        } else if let Some(overhead) = message.strip_prefix(END) {
+            let overhead = overhead.trim().parse().unwrap_or(0);
+            return self.close(overhead);
+        } else if let Some(label) = message.strip_prefix(SECTION) {
+            if self.frames.last().map_or(false, |f| !f.scoped) {
+                self.frames.pop();
+            }
+            self.open(label, false);
+        }
+        None
+    }
+
+    fn open(&mut self, label: &str, scoped: bool) {
+        self.frames.push(Frame {
+            label: label.trim().to_string(),
+            before: None,
+            after: None,
+            scoped,
+        });
+    }
+
+    fn close(&mut self, overhead: u64) -> Option<Measurement> {
+        let frame = self.frames.pop()?;
+        let (before, after) = (frame.before?, frame.after?);
+        let raw = before.saturating_sub(after);
+        Some(Measurement {
+            instruction: self.instruction.clone().unwrap_or_default(),
+            section: frame.label,
+            before,
+            after,
+            raw,
+            overhead,
+            units: raw.saturating_sub(overhead),
+        })
+    }
+}
+
+/// Parses a complete log, returning the measurements in the order they were emitted.
+pub fn parse_log(log: &str) -> Vec<Measurement> {
+    let mut parser = LogParser::new();
+    log.lines().filter_map(|l| parser.push_line(l)).collect()
+}
+
+fn after<'a>(line: &'a str, pattern: &str) -> Option<&'a str> {
+    line.find(pattern).map(|i| &line[i + pattern.len()..])
+}
+
+fn parse_consumption(line: &str) -> Option<u64> {
+    after(line, CONSUMPTION)?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = "\
+[2023-05-02T09:12:01.123456789Z DEBUG solana_runtime::message_processor::stable_log] Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS invoke [1]
+[2023-05-02T09:12:01.123456789Z DEBUG solana_runtime::message_processor::stable_log] Program log: Instruction: RingBuf
+[2023-05-02T09:12:01.123456789Z DEBUG solana_runtime::message_processor::stable_log] Program log: # Inserting_488
+[2023-05-02T09:12:01.123456789Z DEBUG solana_runtime::message_processor::stable_log] Program consumption: 1398000 units remaining
+[2023-05-02T09:12:01.123456789Z DEBUG solana_runtime::message_processor::stable_log] Program consumption: 1250000 units remaining
+[2023-05-02T09:12:01.123456789Z DEBUG solana_runtime::message_processor::stable_log] Program log: # Iterating_10
+[2023-05-02T09:12:01.123456789Z DEBUG solana_runtime::message_processor::stable_log] Program consumption: 1200000 units remaining
+[2023-05-02T09:12:01.123456789Z DEBUG solana_runtime::message_processor::stable_log] Program consumption: 1199500 units remaining
+[2023-05-02T09:12:01.123456789Z DEBUG solana_runtime::message_processor::stable_log] Program log: Instruction: DLList
+[2023-05-02T09:12:01.123456789Z DEBUG solana_runtime::message_processor::stable_log] Program log: # Initialize
+[2023-05-02T09:12:01.123456789Z DEBUG solana_runtime::message_processor::stable_log] Program consumption: 1390000 units remaining
+[2023-05-02T09:12:01.123456789Z DEBUG solana_runtime::message_processor::stable_log] Program consumption: 1380000 units remaining
+";
+
+    #[test]
+    fn pairs_labels_with_readings() {
+        let measurements = parse_log(LOG);
+        assert_eq!(
+            measurements,
+            vec![
+                Measurement {
+                    instruction: "RingBuf".into(),
+                    section: "Inserting_488".into(),
+                    before: 1398000,
+                    after: 1250000,
+                    raw: 148000,
+                    overhead: 0,
+                    units: 148000,
+                },
+                Measurement {
+                    instruction: "RingBuf".into(),
+                    section: "Iterating_10".into(),
+                    before: 1200000,
+                    after: 1199500,
+                    raw: 500,
+                    overhead: 0,
+                    units: 500,
+                },
+                Measurement {
+                    instruction: "DLList".into(),
+                    section: "Initialize".into(),
+                    before: 1390000,
+                    after: 1380000,
+                    raw: 10000,
+                    overhead: 0,
+                    units: 10000,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_readings_outside_sections() {
+        let log = "\
+Program log: Instruction: RingBuf
+Program consumption: 1000 units remaining
+Program log: # Deleting_488
+Program consumption: 900 units remaining
+Program consumption: 400 units remaining
+Program consumption: 300 units remaining
+";
+        let measurements = parse_log(log);
+        assert_eq!(measurements.len(), 1);
+        assert_eq!(measurements[0].section, "Deleting_488");
+        assert_eq!(measurements[0].units, 500);
+    }
+
+    #[test]
+    fn new_instruction_discards_open_section() {
+        let log = "\
+Program log: Instruction: RingBuf
+Program log: # Inserting_488
+Program consumption: 1000 units remaining
+Program log: Instruction: DLList
+Program consumption: 900 units remaining
+";
+        assert!(parse_log(log).is_empty());
+    }
+
+    #[test]
+    fn scoped_sections_subtract_overhead() {
+        let log = "\
+Program log: Instruction: RingBuf
+Program log: #begin Inserting_488
+Program consumption: 1000 units remaining
+Program consumption: 400 units remaining
+Program log: #end 100
+";
+        let measurements = parse_log(log);
+        assert_eq!(measurements.len(), 1);
+        assert_eq!(measurements[0].section, "Inserting_488");
+        assert_eq!(measurements[0].overhead, 100);
+        assert_eq!(measurements[0].units, 500);
+    }
+
+    #[test]
+    fn nested_scoped_sections() {
+        let log = "\
+Program log: Instruction: DLList
+Program log: #begin Outer
+Program consumption: 1000 units remaining
+Program log: #begin Inner
+Program consumption: 900 units remaining
+Program consumption: 700 units remaining
+Program log: #end 100
+Program consumption: 500 units remaining
+Program log: #end 100
+";
+        let measurements = parse_log(log);
+        assert_eq!(measurements.len(), 2);
+        assert_eq!(measurements[0].section, "Inner");
+        assert_eq!(measurements[0].units, 100);
+        assert_eq!(measurements[1].section, "Outer");
+        assert_eq!(measurements[1].units, 400);
+    }
+
+    #[test]
+    fn bench_marker_renames_instruction() {
+        let log = "\
+Program log: Instruction: RingBuf
+Program log: #bench RingBuf_64
+Program log: #begin Initialize
+Program consumption: 1000 units remaining
+Program consumption: 800 units remaining
+Program log: #end 100
+Program log: #bench RingBuf_256
+Program log: #begin Initialize
+Program consumption: 700 units remaining
+Program consumption: 500 units remaining
+Program log: #end 100
+";
+        let measurements = parse_log(log);
+        assert_eq!(measurements.len(), 2);
+        assert_eq!(measurements[0].instruction, "RingBuf_64");
+        assert_eq!(measurements[1].instruction, "RingBuf_256");
+    }
+}
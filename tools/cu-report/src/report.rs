@@ -0,0 +1,82 @@
+use crate::{compare::Comparison, parser::Measurement};
+use std::{error::Error, fs, io::Write, path::Path};
+
+pub const JSON_REPORT: &str = "report.json";
+pub const CSV_REPORT: &str = "report.csv";
+pub const COMPARISON_REPORT: &str = "comparison.json";
+
+/// Writes measurements as a JSON array with one object per [`Measurement`].
+pub fn write_json(writer: impl Write, measurements: &[Measurement]) -> serde_json::Result<()> {
+    serde_json::to_writer_pretty(writer, measurements)
+}
+
+/// Writes measurements as CSV with one column per [`Measurement`] field.
+pub fn write_csv(writer: impl Write, measurements: &[Measurement]) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    for measurement in measurements {
+        writer.serialize(measurement)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the JSON, CSV and comparison reports into `dir`, creating it if needed.
+pub fn write_reports(
+    dir: &Path,
+    measurements: &[Measurement],
+    comparison: &Comparison,
+) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+    write_json(fs::File::create(dir.join(JSON_REPORT))?, measurements)?;
+    write_csv(fs::File::create(dir.join(CSV_REPORT))?, measurements)?;
+    serde_json::to_writer_pretty(fs::File::create(dir.join(COMPARISON_REPORT))?, comparison)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurements() -> Vec<Measurement> {
+        vec![
+            Measurement {
+                instruction: "RingBuf".into(),
+                section: "Inserting_488".into(),
+                before: 1398000,
+                after: 1250000,
+                raw: 148000,
+                overhead: 0,
+                units: 148000,
+            },
+            Measurement {
+                instruction: "DLList".into(),
+                section: "Removing_20_random_positions".into(),
+                before: 1200000,
+                after: 1195000,
+                raw: 5000,
+                overhead: 100,
+                units: 4900,
+            },
+        ]
+    }
+
+    #[test]
+    fn csv_has_one_row_per_section() {
+        let mut out = Vec::new();
+        write_csv(&mut out, &measurements()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "instruction,section,before,after,raw,overhead,units\n\
+             RingBuf,Inserting_488,1398000,1250000,148000,0,148000\n\
+             DLList,Removing_20_random_positions,1200000,1195000,5000,100,4900\n"
+        );
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let mut out = Vec::new();
+        write_json(&mut out, &measurements()).unwrap();
+        let parsed: Vec<Measurement> = serde_json::from_slice(&out).unwrap();
+        assert_eq!(parsed, measurements());
+    }
+}
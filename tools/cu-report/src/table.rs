@@ -0,0 +1,48 @@
+use crate::parser::Measurement;
+
+/// Renders measurements as a column-aligned `instruction section raw units` table.
+pub fn render(measurements: &[Measurement]) -> String {
+    let rows: Vec<Vec<String>> = measurements
+        .iter()
+        .map(|m| {
+            vec![
+                m.instruction.clone(),
+                m.section.clone(),
+                m.raw.to_string(),
+                m.units.to_string(),
+            ]
+        })
+        .collect();
+    align(&rows, 2)
+}
+
+/// Aligns `rows` into columns separated by two spaces. The first `text_columns` columns are
+/// left-aligned, the remaining (numeric) ones right-aligned.
+pub fn align(rows: &[Vec<String>], text_columns: usize) -> String {
+    let mut widths = Vec::new();
+    for row in rows {
+        widths.resize(widths.len().max(row.len()), 0);
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let mut out = String::new();
+    for row in rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (cell, &width))| {
+                if i < text_columns {
+                    format!("{cell:<width$}")
+                } else {
+                    format!("{cell:>width$}")
+                }
+            })
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}